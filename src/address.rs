@@ -1,4 +1,8 @@
-use elements::{secp256k1_zkp, Script, PubkeyHash, ScriptHash, WPubkeyHash, WScriptHash};
+use std::collections::BTreeMap;
+
+use elements::{secp256k1_zkp, slip77, Script, PubkeyHash, ScriptHash, WPubkeyHash, WScriptHash};
+use elements::secp256k1_zkp::XOnlyPublicKey;
+use elements::taproot::TapNodeHash;
 use bitcoin::PublicKey;
 use elements::Address;
 use serde::{Deserialize, Serialize};
@@ -14,6 +18,8 @@ pub struct AddressInfo {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub witness_program_version: Option<usize>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_program: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub pubkey_hash: Option<PubkeyHash>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub script_hash: Option<ScriptHash>,
@@ -25,6 +31,9 @@ pub struct AddressInfo {
 	pub blinding_pubkey: Option<secp256k1_zkp::PublicKey>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub unconfidential: Option<Address>,
+	/// The same address under the params of each network.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub all_networks: BTreeMap<String, Address>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
@@ -41,26 +50,124 @@ pub struct Addresses {
 	pub p2wsh: Option<Address>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub p2shwsh: Option<Address>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub p2tr: Option<Address>,
+	/// The scriptPubKey of each address type.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub script_pubkeys: BTreeMap<String, ::HexBytes>,
+	/// The output descriptor of each address type.
+	///
+	/// These are omitted for addresses with a given blinding pubkey, because a `ct()` descriptor
+	/// with a single blinding key tweaks it with the scriptPubKey.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub descriptors: BTreeMap<String, String>,
+	/// The blinding pubkey of each address type, when derived with SLIP-77.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub blinding_pubkeys: BTreeMap<String, secp256k1_zkp::PublicKey>,
 }
 
 impl Addresses {
 	pub fn from_pubkey(pubkey: &PublicKey, blinder: Option<secp256k1_zkp::PublicKey>, network: Network) -> Addresses {
 		let params = network.address_params();
-		Addresses {
+		let mut ret = Addresses {
 			p2pkh: Some(Address::p2pkh(pubkey, blinder, params)),
 			p2wpkh: Some(Address::p2wpkh(pubkey, blinder, params)),
 			p2shwpkh: Some(Address::p2shwpkh(pubkey, blinder, params)),
 			..Default::default()
+		};
+		if blinder.is_none() {
+			ret.descriptors.insert("p2pkh".to_owned(), format!("elpkh({})", pubkey));
+			ret.descriptors.insert("p2wpkh".to_owned(), format!("elwpkh({})", pubkey));
+			ret.descriptors.insert("p2shwpkh".to_owned(), format!("elsh(wpkh({}))", pubkey));
 		}
+		ret.set_script_pubkeys();
+		ret
 	}
 
+	/// There are no descriptors for arbitrary scripts, so only the scriptPubKeys are added.
 	pub fn from_script(script: &Script, blinder: Option<secp256k1_zkp::PublicKey>, network: Network) -> Addresses {
 		let params = network.address_params();
-		Addresses {
+		let mut ret = Addresses {
 			p2sh: Some(Address::p2sh(&script, blinder, params)),
 			p2wsh: Some(Address::p2wsh(&script, blinder, params)),
 			p2shwsh: Some(Address::p2shwsh(&script, blinder, params)),
 			..Default::default()
+		};
+		ret.set_script_pubkeys();
+		ret
+	}
+
+	/// Create the taproot address for the given internal key, tweaked with the
+	/// merkle root of the script tree, if any.
+	pub fn from_taproot_internal_key(
+		internal_key: XOnlyPublicKey,
+		merkle_root: Option<TapNodeHash>,
+		blinder: Option<secp256k1_zkp::PublicKey>,
+		network: Network,
+	) -> Addresses {
+		let secp = secp256k1_zkp::Secp256k1::verification_only();
+		let params = network.address_params();
+		let mut ret = Addresses {
+			p2tr: Some(Address::p2tr(&secp, internal_key, merkle_root, blinder, params)),
+			..Default::default()
+		};
+		// The script tree can't be recovered from its merkle root.
+		if merkle_root.is_none() && blinder.is_none() {
+			ret.descriptors.insert("p2tr".to_owned(), format!("eltr({})", internal_key));
+		}
+		ret.set_script_pubkeys();
+		ret
+	}
+
+	/// Make the addresses confidential using the blinding keys derived with SLIP-77 from the
+	/// scriptPubKey of each address.
+	pub fn blind_with_slip77(&mut self, master_blinding_key: &slip77::MasterBlindingKey) {
+		let secp = secp256k1_zkp::Secp256k1::signing_only();
+		let blind = |address: &mut Option<Address>| {
+			address.as_mut().map(|a| {
+				let pk = master_blinding_key.blinding_key(&secp, &a.script_pubkey());
+				*a = a.to_confidential(pk);
+				pk
+			})
+		};
+
+		let addresses = vec![
+			("p2pkh", &mut self.p2pkh),
+			("p2wpkh", &mut self.p2wpkh),
+			("p2shwpkh", &mut self.p2shwpkh),
+			("p2sh", &mut self.p2sh),
+			("p2wsh", &mut self.p2wsh),
+			("p2shwsh", &mut self.p2shwsh),
+			("p2tr", &mut self.p2tr),
+		];
+		for (name, address) in addresses {
+			if let Some(pk) = blind(address) {
+				self.blinding_pubkeys.insert(name.to_owned(), pk);
+			}
+		}
+
+		let master_hex = hex::encode(master_blinding_key.0.secret_bytes());
+		for desc in self.descriptors.values_mut() {
+			*desc = format!("ct(slip77({}),{})", master_hex, desc);
+		}
+	}
+
+	fn set_script_pubkeys(&mut self) {
+		let addresses = [
+			("p2pkh", &self.p2pkh),
+			("p2wpkh", &self.p2wpkh),
+			("p2shwpkh", &self.p2shwpkh),
+			("p2sh", &self.p2sh),
+			("p2wsh", &self.p2wsh),
+			("p2shwsh", &self.p2shwsh),
+			("p2tr", &self.p2tr),
+		];
+		let mut script_pubkeys = BTreeMap::new();
+		for &(name, address) in addresses.iter() {
+			if let Some(ref address) = *address {
+				script_pubkeys.insert(name.to_owned(), address.script_pubkey().to_bytes().into());
+			}
 		}
+		self.script_pubkeys = script_pubkeys;
 	}
 }
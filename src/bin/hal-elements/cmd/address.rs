@@ -1,47 +1,199 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
 use elements::hashes::Hash;
 use elements::Address;
-use elements::{secp256k1_zkp, WPubkeyHash, WScriptHash};
+use elements::{secp256k1_zkp, slip77, WPubkeyHash, WScriptHash};
+use elements::secp256k1_zkp::XOnlyPublicKey;
+use elements::taproot::TapNodeHash;
+use bitcoin::bip32;
 use bitcoin::PublicKey;
 use clap;
 
 use cmd;
 use hal;
 use hex;
+use serde::Serialize;
 
 use ::{Network};
 
 pub fn subcommand<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand_group("address", "work with addresses")
+		.subcommand(cmd_convert())
 		.subcommand(cmd_create())
 		.subcommand(cmd_inspect())
+		.subcommand(cmd_network())
 }
 
 pub fn execute<'a>(matches: &clap::ArgMatches<'a>) {
 	match matches.subcommand() {
+		("convert", Some(ref m)) => exec_convert(&m),
 		("create", Some(ref m)) => exec_create(&m),
 		("inspect", Some(ref m)) => exec_inspect(&m),
+		("network", Some(ref m)) => exec_network(&m),
 		(_, _) => unreachable!("clap prints help"),
 	};
 }
 
+pub fn parse_blinder(b: &str) -> secp256k1_zkp::PublicKey {
+	let bytes = hex::decode(b).expect("invaid blinder hex");
+	secp256k1_zkp::PublicKey::from_slice(&bytes).expect("invalid blinder")
+}
+
+fn cmd_convert<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("convert", "convert between confidential and unconfidential addresses").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::arg("address", "the address").required(true),
+		cmd::opt("blinder", "a blinding pubkey in hex to make the address confidential")
+			.takes_value(true)
+			.required(false),
+	])
+}
+
+/// An address together with its info.
+#[derive(Serialize)]
+pub struct AddressOutput {
+	pub address: Address,
+	#[serde(flatten)]
+	pub info: hal_elements::address::AddressInfo,
+}
+
+fn exec_convert<'a>(matches: &clap::ArgMatches<'a>) {
+	let address_str = matches.value_of("address").expect("no address provided");
+	let address: Address = address_str.parse().expect("invalid address format");
+
+	let converted = match (address.blinding_pubkey, matches.value_of("blinder")) {
+		(Some(_), Some(_)) => panic!("Address is already confidential."),
+		(None, None) => panic!("Address is not confidential, provide a blinder to convert it."),
+		(Some(_), None) => Address {
+			params: address.params,
+			payload: address.payload.clone(),
+			blinding_pubkey: None,
+		},
+		(None, Some(b)) => Address {
+			params: address.params,
+			payload: address.payload.clone(),
+			blinding_pubkey: Some(parse_blinder(b)),
+		},
+	};
+
+	let info = address_info(&converted);
+	cmd::print_output(matches, &AddressOutput {
+		address: converted,
+		info: info,
+	})
+}
+
 fn cmd_create<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("create", "create addresses").args(&cmd::opts_networks()).args(&[
 		cmd::opt_yaml(),
+		cmd::opt_cbor(),
 		cmd::opt("pubkey", "a public key in hex").takes_value(true).required(false),
 		cmd::opt("script", "a script in hex").takes_value(true).required(false),
 		cmd::opt("blinder", "a blinding pubkey in hex").takes_value(true).required(false),
+		cmd::opt("slip77", "a SLIP-77 master blinding key in hex to derive the blinding pubkeys")
+			.takes_value(true)
+			.conflicts_with("blinder")
+			.required(false),
+		cmd::opt("taproot", "create a taproot address with the pubkey as x-only internal key")
+			.requires("pubkey")
+			.required(false),
+		cmd::opt("merkle-root", "the taproot script tree merkle root in hex")
+			.takes_value(true)
+			.requires("taproot")
+			.required(false),
+		cmd::opt("xpub", "create the addresses of a range of child keys of this xpub")
+			.takes_value(true)
+			.requires("range")
+			.conflicts_with_all(&["pubkey", "script", "taproot"])
+			.required(false),
+		cmd::opt("range", "the range of child indices to derive as <start>..<end>, end exclusive")
+			.takes_value(true)
+			.requires("xpub")
+			.required(false),
+		cmd::opt("change", "derive from the change branch (1) instead of the receive branch (0)")
+			.requires("xpub")
+			.required(false),
 	])
 }
 
+/// The addresses of a child key derived from an xpub.
+#[derive(Serialize)]
+struct DerivedAddresses {
+	index: u32,
+	pubkey: PublicKey,
+	#[serde(flatten)]
+	addresses: hal_elements::address::Addresses,
+}
+
+/// Parse a range of child indices given as `<start>..<end>`.
+fn parse_range(range: &str) -> ::std::ops::Range<u32> {
+	let mut parts = range.splitn(2, "..");
+	let start = parts.next().unwrap().parse::<u32>().expect("invalid range start");
+	let end = parts.next().expect("range must be <start>..<end>")
+		.parse::<u32>().expect("invalid range end");
+	if start >= end {
+		panic!("empty range: {}", range);
+	}
+	start..end
+}
+
+fn exec_create_xpub<'a>(matches: &clap::ArgMatches<'a>, xpub: &str) {
+	let network = cmd::network(matches);
+	let xpub = bip32::ExtendedPubKey::from_str(xpub).expect("invalid xpub");
+	let blinder = matches.value_of("blinder").map(parse_blinder);
+	let slip77 = matches.value_of("slip77").map(|master| {
+		slip77::MasterBlindingKey(
+			secp256k1_zkp::SecretKey::from_str(master).expect("invalid SLIP-77 master key"),
+		)
+	});
+
+	let secp = secp256k1_zkp::Secp256k1::verification_only();
+	let branch = if matches.is_present("change") { 1 } else { 0 };
+	let branch = xpub
+		.derive_pub(&secp, &[bip32::ChildNumber::from_normal_idx(branch).unwrap()])
+		.expect("failed to derive branch xpub");
+	let derived = parse_range(matches.value_of("range").unwrap()).map(|index| {
+		let child = bip32::ChildNumber::from_normal_idx(index).expect("invalid child index");
+		let pubkey = PublicKey::new(
+			branch.derive_pub(&secp, &[child]).expect("failed to derive child xpub").public_key,
+		);
+		let mut addresses = hal_elements::address::Addresses::from_pubkey(&pubkey, blinder, network);
+		if let Some(ref master) = slip77 {
+			addresses.blind_with_slip77(master);
+		}
+		DerivedAddresses {
+			index: index,
+			pubkey: pubkey,
+			addresses: addresses,
+		}
+	}).collect::<Vec<_>>();
+	cmd::print_output(matches, &derived)
+}
+
 fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
+	if let Some(xpub) = matches.value_of("xpub") {
+		return exec_create_xpub(matches, xpub);
+	}
 	let network = cmd::network(matches);
 
-	let blinder = matches.value_of("blinder").map(|b| {
-		let bytes = hex::decode(b).expect("invaid blinder hex");
-		secp256k1_zkp::PublicKey::from_slice(&bytes).expect("invalid blinder")
-	});
+	let blinder = matches.value_of("blinder").map(parse_blinder);
 
-	let created = if let Some(pubkey_hex) = matches.value_of("pubkey") {
+	let mut created = if matches.is_present("taproot") {
+		let pubkey_hex = matches.value_of("pubkey").expect("no pubkey provided");
+		let internal_key: XOnlyPublicKey = pubkey_hex.parse().expect("invalid x-only pubkey");
+		let merkle_root = matches.value_of("merkle-root").map(|r| {
+			r.parse::<TapNodeHash>().expect("invalid merkle root")
+		});
+		hal_elements::address::Addresses::from_taproot_internal_key(
+			internal_key,
+			merkle_root,
+			blinder,
+			network,
+		)
+	} else if let Some(pubkey_hex) = matches.value_of("pubkey") {
 		let pubkey: PublicKey = pubkey_hex.parse().expect("invalid pubkey");
 		hal_elements::address::Addresses::from_pubkey(&pubkey, blinder, network)
 	} else if let Some(script_hex) = matches.value_of("script") {
@@ -53,17 +205,115 @@ fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
 		panic!("Can't create addresses without a pubkey");
 	};
 
+	if let Some(master) = matches.value_of("slip77") {
+		let master = secp256k1_zkp::SecretKey::from_str(master).expect("invalid SLIP-77 master key");
+		created.blind_with_slip77(&slip77::MasterBlindingKey(master));
+	}
+
 	cmd::print_output(matches, &created)
 }
 
 fn cmd_inspect<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("inspect", "inspect addresses")
-		.args(&[cmd::opt_yaml(), cmd::arg("address", "the address").required(true)])
+	cmd::subcommand("inspect", "inspect addresses").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::arg("address", "the address").required_unless("batch"),
+		cmd::opt("batch", "inspect newline-separated addresses read from stdin")
+			.conflicts_with("address")
+			.required(false),
+		cmd::opt("all-networks", "also show the address under the params of each network")
+			.required(false),
+	])
+}
+
+/// The same address under the params of each network, keyed by network name.
+fn all_networks(address: &Address) -> BTreeMap<String, Address> {
+	let networks = [
+		("elementsregtest", Network::ElementsRegtest),
+		("liquid", Network::Liquid),
+		("liquidtestnet", Network::LiquidTestnet),
+	];
+	networks.iter().map(|&(name, network)| {
+		(name.to_owned(), Address {
+			params: network.address_params(),
+			payload: address.payload.clone(),
+			blinding_pubkey: address.blinding_pubkey,
+		})
+	}).collect()
+}
+
+/// An entry in the output of a batch inspection.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BatchEntry {
+	Info(hal_elements::address::AddressInfo),
+	Error {
+		address: String,
+		error: String,
+	},
 }
 
 fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
+	if matches.is_present("batch") {
+		let stdin = io::stdin();
+		let entries = stdin
+			.lock()
+			.lines()
+			.map(|l| l.expect("failed to read from stdin"))
+			.filter(|l| !l.trim().is_empty())
+			.map(|l| match l.trim().parse::<Address>() {
+				Ok(address) => {
+					let mut info = address_info(&address);
+					if matches.is_present("all-networks") {
+						info.all_networks = all_networks(&address);
+					}
+					BatchEntry::Info(info)
+				}
+				Err(e) => BatchEntry::Error {
+					address: l.trim().to_owned(),
+					error: e.to_string(),
+				},
+			})
+			.collect::<Vec<_>>();
+		cmd::print_output(matches, &entries)
+	} else {
+		let address_str = matches.value_of("address").expect("no address provided");
+		let address: Address = address_str.parse().expect("invalid address format");
+		let mut info = address_info(&address);
+		if matches.is_present("all-networks") {
+			info.all_networks = all_networks(&address);
+		}
+		cmd::print_output(matches, &info)
+	}
+}
+
+fn cmd_network<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("network", "detect the network of an address").args(&cmd::opts_networks()).args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::arg("address", "the address").required(true),
+	])
+}
+
+fn exec_network<'a>(matches: &clap::ArgMatches<'a>) {
 	let address_str = matches.value_of("address").expect("no address provided");
-	let address: Address = address_str.parse().expect("invalid address format");
+	// Addresses of a custom network given with the network options are detected first.
+	let network = match cmd::network(matches) {
+		Network::Custom(custom) => {
+			let address = Address::parse_with_params(address_str, &custom.address_params)
+				.or_else(|_| address_str.parse::<Address>())
+				.expect("invalid address format");
+			Network::from_params_with_custom(address.params, custom)
+		}
+		_ => {
+			let address: Address = address_str.parse().expect("invalid address format");
+			Network::from_params(address.params)
+		}
+	};
+	cmd::print_output(matches, &network.expect("unknown address network"))
+}
+
+pub fn address_info(address: &Address) -> hal_elements::address::AddressInfo {
 	let script_pk = address.script_pubkey();
 
 	let mut info = hal_elements::address::AddressInfo {
@@ -80,6 +330,7 @@ fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
 		witness_pubkey_hash: None,
 		witness_script_hash: None,
 		witness_program_version: None,
+		witness_program: None,
 		blinding_pubkey: address.blinding_pubkey,
 		unconfidential: if address.blinding_pubkey.is_some() {
 			Some(Address {
@@ -90,10 +341,11 @@ fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
 		} else {
 			None
 		},
+		all_networks: BTreeMap::new(),
 	};
 
 	use elements::address::Payload;
-	match address.payload {
+	match address.payload.clone() {
 		Payload::PubkeyHash(pkh) => {
 			info.type_ = Some("p2pkh".to_owned());
 			info.pubkey_hash = Some(pkh);
@@ -108,6 +360,7 @@ fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
 		} => {
 			let version = version.to_u8() as usize;
 			info.witness_program_version = Some(version);
+			info.witness_program = Some(program.clone().into());
 
 			if version == 0 {
 				if program.len() == 20 {
@@ -121,11 +374,13 @@ fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
 				} else {
 					info.type_ = Some("invalid-witness-program".to_owned());
 				}
+			} else if version == 1 && program.len() == 32 {
+				info.type_ = Some("p2tr".to_owned());
 			} else {
 				info.type_ = Some("unknown-witness-program-version".to_owned());
 			}
 		}
 	}
 
-	cmd::print_output(matches, &info)
+	info
 }
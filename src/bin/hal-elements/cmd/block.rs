@@ -1,11 +1,14 @@
-use std::io::Write;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 
-use elements::encode::{deserialize, serialize};
-use elements::{dynafed, Block, BlockExtData, BlockHeader};
+use elements::encode::{deserialize, serialize, Decodable, VarInt};
+use elements::{dynafed, AssetId, Block, BlockExtData, BlockHeader, Transaction};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
 
 use cmd;
-use cmd::tx::create_transaction;
-use hal_elements::block::{BlockHeaderInfo, BlockInfo, ParamsInfo, ParamsType};
+use hal_elements::block::{add_fees, BlockCountInfo, BlockHeaderInfo, BlockInfo, ParamsInfo, ParamsType};
+use hal_elements::{GetInfo, Network};
 
 pub fn subcommand<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand_group("block", "manipulate blocks")
@@ -70,15 +73,22 @@ fn create_params(info: ParamsInfo) -> dynafed::Params {
 	}
 }
 
-fn create_block_header(info: BlockHeaderInfo) -> BlockHeader {
+fn create_block_header(info: BlockHeaderInfo, txdata: &[Transaction]) -> BlockHeader {
 	if info.block_hash.is_some() {
 		warn!("Field \"block_hash\" is ignored.");
 	}
 
+	let merkle_root = hal_elements::block::merkle_root(txdata);
+	if let Some(root) = info.merkle_root {
+		if root != merkle_root {
+			panic!("Field \"merkle_root\" does not match the transactions: expected {}", merkle_root);
+		}
+	}
+
 	BlockHeader {
 		version: info.version,
 		prev_blockhash: info.previous_block_hash,
-		merkle_root: info.merkle_root,
+		merkle_root: merkle_root,
 		time: info.time,
 		height: info.height,
 		ext: if info.dynafed {
@@ -109,17 +119,24 @@ fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
 		warn!("Field \"txids\" is ignored.");
 	}
 
+	let txdata: Vec<Transaction> = match (info.transactions, info.raw_transactions) {
+		(Some(_), Some(_)) => panic!("Can't provide transactions both in JSON and raw."),
+		(None, None) => panic!("No transactions provided."),
+		(Some(infos), None) => infos
+			.into_iter()
+			.map(|info| match hal_elements::tx::create_transaction(info, None, &mut None) {
+				Ok(tx) => tx,
+				Err(e) => panic!("Invalid transaction info: {}", e),
+			})
+			.collect(),
+		(None, Some(raws)) => raws
+			.into_iter()
+			.map(|r| deserialize(&r.0).expect("invalid raw transaction"))
+			.collect(),
+	};
 	let block = Block {
-		header: create_block_header(info.header),
-		txdata: match (info.transactions, info.raw_transactions) {
-			(Some(_), Some(_)) => panic!("Can't provide transactions both in JSON and raw."),
-			(None, None) => panic!("No transactions provided."),
-			(Some(infos), None) => infos.into_iter().map(create_transaction).collect(),
-			(None, Some(raws)) => raws
-				.into_iter()
-				.map(|r| deserialize(&r.0).expect("invalid raw transaction"))
-				.collect(),
-		},
+		header: create_block_header(info.header, &txdata),
+		txdata: txdata,
 	};
 
 	let block_bytes = serialize(&block);
@@ -133,26 +150,127 @@ fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
 fn cmd_decode<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("decode", "decode a raw block to JSON").args(&cmd::opts_networks()).args(&[
 		cmd::opt_yaml(),
+		cmd::opt_cbor(),
 		cmd::arg("raw-block", "the raw block in hex").required(true),
 		cmd::opt("txids", "provide transactions IDs instead of full transactions"),
+		cmd::opt("summary", "only provide the header, the transaction IDs and the block stats"),
+		cmd::opt("count", "only provide the number of transactions, inputs, outputs and issuances")
+			.conflicts_with_all(&["txids", "summary"]),
 	])
 }
 
+/// The running totals of the transactions of a streamed block.
+struct StreamStats {
+	txs_size: usize,
+	txs_weight: usize,
+	total_fee: BTreeMap<AssetId, u64>,
+}
+
+/// A raw block that is serialized as [BlockInfo] while decoding its transactions one by one,
+/// so that only a single transaction is decoded at a time.
+///
+/// The output is only streamed when it's written directly: with `--asset-labels` or
+/// `--btc-amounts`, [cmd::print_output_network] converts the whole block info to a JSON value first,
+/// so it's kept in memory.
+struct BlockStream<'a> {
+	network: Network,
+	header: BlockHeaderInfo,
+	raw_size: usize,
+	tx_count: usize,
+	reader: RefCell<io::Cursor<&'a [u8]>>,
+	stats: RefCell<StreamStats>,
+}
+
+impl<'a> BlockStream<'a> {
+	fn new(raw: &'a [u8], network: Network) -> BlockStream<'a> {
+		let mut reader = io::Cursor::new(raw);
+		let header = BlockHeader::consensus_decode(&mut reader).expect("invalid block header");
+		let tx_count = VarInt::consensus_decode(&mut reader).expect("invalid block format").0;
+
+		// Check all transactions before anything is written, so that an invalid block
+		// doesn't leave partial output behind.
+		let txs_start = reader.position();
+		for i in 0..tx_count {
+			if let Err(e) = Transaction::consensus_decode(&mut reader) {
+				panic!("invalid transaction #{}: {}", i, e);
+			}
+		}
+		if reader.position() as usize != raw.len() {
+			panic!("invalid block format: {} trailing bytes", raw.len() - reader.position() as usize);
+		}
+		reader.set_position(txs_start);
+
+		BlockStream {
+			network: network,
+			header: header.get_info(network),
+			raw_size: raw.len(),
+			tx_count: tx_count as usize,
+			reader: RefCell::new(reader),
+			stats: RefCell::new(StreamStats {
+				txs_size: 0,
+				txs_weight: 0,
+				total_fee: BTreeMap::new(),
+			}),
+		}
+	}
+}
+
+struct StreamTransactions<'a: 'b, 'b>(&'b BlockStream<'a>);
+
+impl<'a, 'b> Serialize for StreamTransactions<'a, 'b> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let stream = self.0;
+		let mut seq = serializer.serialize_seq(Some(stream.tx_count))?;
+		for _ in 0..stream.tx_count {
+			let tx = Transaction::consensus_decode(&mut *stream.reader.borrow_mut())
+				.map_err(|e| ser::Error::custom(format!("invalid transaction: {}", e)))?;
+
+			let mut stats = stream.stats.borrow_mut();
+			stats.txs_size += tx.size();
+			stats.txs_weight += tx.weight();
+			if !tx.is_coinbase() {
+				add_fees(&mut stats.total_fee, &tx, stream.network);
+			}
+			seq.serialize_element(&tx.get_info(stream.network))?;
+		}
+		seq.end()
+	}
+}
+
+impl<'a> Serialize for BlockStream<'a> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		// Same fields as BlockInfo, the stats are only known after the transactions.
+		let mut map = serializer.serialize_map(Some(6))?;
+		map.serialize_entry("header", &self.header)?;
+		map.serialize_entry("transactions", &StreamTransactions(self))?;
+		let stats = self.stats.borrow();
+		map.serialize_entry("tx_count", &self.tx_count)?;
+		map.serialize_entry("total_size", &self.raw_size)?;
+		map.serialize_entry(
+			"total_weight",
+			&((self.raw_size - stats.txs_size) * 4 + stats.txs_weight),
+		)?;
+		map.serialize_entry("total_fee", &stats.total_fee)?;
+		map.end()
+	}
+}
+
 fn exec_decode<'a>(matches: &clap::ArgMatches<'a>) {
 	let hex_tx = matches.value_of("raw-block").expect("no raw block provided");
 	let raw_tx = hex::decode(hex_tx).expect("could not decode raw block hex");
-	let block: Block = deserialize(&raw_tx).expect("invalid block format");
-
-	if matches.is_present("txids") {
-		let info = BlockInfo {
-			header: ::GetInfo::get_info(&block.header, cmd::network(matches)),
-			txids: Some(block.txdata.iter().map(|t| t.txid()).collect()),
-			transactions: None,
-			raw_transactions: None,
-		};
+	let network = cmd::network(matches);
+
+	if matches.is_present("count") {
+		let block: Block = deserialize(&raw_tx).expect("invalid block format");
+		let info = BlockCountInfo::from_block(&block);
 		cmd::print_output(matches, &info)
+	} else if matches.is_present("txids") || matches.is_present("summary") {
+		let block: Block = deserialize(&raw_tx).expect("invalid block format");
+		let info = BlockInfo::summary(&block, network);
+		cmd::print_output_network(matches, &info, network)
 	} else {
-		let info = ::GetInfo::get_info(&block, cmd::network(matches));
-		cmd::print_output(matches, &info)
+		// Decode the transactions while printing them to avoid keeping all their info in memory.
+		let stream = BlockStream::new(&raw_tx, network);
+		cmd::print_output_network(matches, &stream, network)
 	}
 }
@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+use clap;
+use elements::secp256k1_zkp::{self, Generator, PedersenCommitment, RangeProof};
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+use elements::{confidential, slip77, AssetId, Script, TxOut, TxOutWitness};
+
+use serde::Serialize;
+
+use cmd;
+
+pub fn subcommand<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand_group("confidential", "work with confidential values and assets")
+		.subcommand(cmd_blinding_key())
+		.subcommand(cmd_commit_asset())
+		.subcommand(cmd_commit_value())
+		.subcommand(cmd_generator())
+		.subcommand(cmd_unblind())
+		.subcommand(cmd_verify_rangeproof())
+}
+
+pub fn execute<'a>(matches: &clap::ArgMatches<'a>) {
+	match matches.subcommand() {
+		("blinding-key", Some(ref m)) => exec_blinding_key(&m),
+		("commit-asset", Some(ref m)) => exec_commit_asset(&m),
+		("commit-value", Some(ref m)) => exec_commit_value(&m),
+		("generator", Some(ref m)) => exec_generator(&m),
+		("unblind", Some(ref m)) => exec_unblind(&m),
+		("verify-rangeproof", Some(ref m)) => exec_verify_rangeproof(&m),
+		(_, _) => unreachable!("clap prints help"),
+	};
+}
+
+fn cmd_blinding_key<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("blinding-key", "derive the SLIP-77 blinding key of a scriptPubKey").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::opt("slip77", "the SLIP-77 master blinding key in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("script-pubkey", "the scriptPubKey in hex").takes_value(true).required(true),
+	])
+}
+
+fn exec_blinding_key<'a>(matches: &clap::ArgMatches<'a>) {
+	let master = secp256k1_zkp::SecretKey::from_str(matches.value_of("slip77").unwrap())
+		.expect("invalid SLIP-77 master key");
+	let script_pubkey: Script = hex::decode(matches.value_of("script-pubkey").unwrap())
+		.expect("invalid script-pubkey hex")
+		.into();
+
+	let info = hal_elements::confidential::BlindingKeyInfo::from_slip77(
+		&slip77::MasterBlindingKey(master),
+		&script_pubkey,
+	);
+	cmd::print_output(matches, &info)
+}
+
+fn cmd_commit_asset<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("commit-asset", "compute the asset generator of a blinded asset").args(&[
+		cmd::opt("asset", "the asset id").takes_value(true).required(true),
+		cmd::opt("blinder", "the asset blinding factor in hex").takes_value(true).required(true),
+	])
+}
+
+/// Compute the blinded asset generator from the `asset` and `asset_blinder_arg` arguments.
+fn asset_generator<'a>(
+	secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
+	matches: &clap::ArgMatches<'a>,
+	asset_blinder_arg: &str,
+) -> Generator {
+	let asset = AssetId::from_str(matches.value_of("asset").unwrap()).expect("invalid asset id");
+	let blinder = match matches.value_of(asset_blinder_arg) {
+		Some(b) => AssetBlindingFactor::from_str(b).expect("invalid asset blinder"),
+		None => AssetBlindingFactor::zero(),
+	};
+	match confidential::Asset::new_confidential(secp, asset, blinder) {
+		confidential::Asset::Confidential(generator) => generator,
+		_ => unreachable!("new_confidential creates a confidential asset"),
+	}
+}
+
+fn exec_commit_asset<'a>(matches: &clap::ArgMatches<'a>) {
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let generator = asset_generator(&secp, matches, "blinder");
+	println!("{}", hex::encode(&generator.serialize()[..]));
+}
+
+fn cmd_commit_value<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("commit-value", "compute the Pedersen commitment of a blinded value").args(&[
+		cmd::opt("value", "the value in satoshi").takes_value(true).required(true),
+		cmd::opt("asset", "the asset id").takes_value(true).required(true),
+		cmd::opt("blinder", "the value blinding factor in hex").takes_value(true).required(true),
+		cmd::opt("asset-blinder", "the asset blinding factor in hex, if the asset is blinded")
+			.takes_value(true)
+			.required(false),
+	])
+}
+
+fn exec_commit_value<'a>(matches: &clap::ArgMatches<'a>) {
+	let value = matches.value_of("value").unwrap().parse::<u64>().expect("invalid value");
+	let blinder = ValueBlindingFactor::from_str(matches.value_of("blinder").unwrap())
+		.expect("invalid value blinder");
+
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let generator = asset_generator(&secp, matches, "asset-blinder");
+	let commitment = match confidential::Value::new_confidential(&secp, value, generator, blinder) {
+		confidential::Value::Confidential(commitment) => commitment,
+		_ => unreachable!("new_confidential creates a confidential value"),
+	};
+	println!("{}", hex::encode(&commitment.serialize()[..]));
+}
+
+fn cmd_generator<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("generator", "compute the asset generator of an asset, blinded or not").args(&[
+		cmd::opt("asset", "the asset id").takes_value(true).required(true),
+		cmd::opt("blinder", "the asset blinding factor in hex, if the asset is blinded")
+			.takes_value(true)
+			.required(false),
+	])
+}
+
+fn exec_generator<'a>(matches: &clap::ArgMatches<'a>) {
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let generator = if matches.is_present("blinder") {
+		asset_generator(&secp, matches, "blinder")
+	} else {
+		let asset = AssetId::from_str(matches.value_of("asset").unwrap()).expect("invalid asset id");
+		Generator::new_unblinded(&secp, asset.into_tag())
+	};
+	println!("{}", hex::encode(&generator.serialize()[..]));
+}
+
+fn cmd_unblind<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("unblind", "rewind the rangeproof of a confidential output").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::opt("value-commitment", "the value commitment in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("asset-commitment", "the asset commitment in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("nonce", "the nonce commitment (the ECDH pubkey) in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("rangeproof", "the rangeproof in hex").takes_value(true).required(true),
+		cmd::opt("blinding-key", "the blinding secret key in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("script-pubkey", "the scriptPubKey of the output in hex, if not empty")
+			.takes_value(true)
+			.required(false),
+	])
+}
+
+fn exec_unblind<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_arg = |name: &str| -> Vec<u8> {
+		hex::decode(matches.value_of(name).unwrap()).expect(&format!("invalid {} hex", name))
+	};
+
+	let value = PedersenCommitment::from_slice(&hex_arg("value-commitment"))
+		.expect("invalid value commitment");
+	let asset =
+		Generator::from_slice(&hex_arg("asset-commitment")).expect("invalid asset commitment");
+	let nonce = secp256k1_zkp::PublicKey::from_slice(&hex_arg("nonce")).expect("invalid nonce");
+	let rangeproof = RangeProof::from_slice(&hex_arg("rangeproof")).expect("invalid rangeproof");
+	let blinding_key = secp256k1_zkp::SecretKey::from_str(matches.value_of("blinding-key").unwrap())
+		.expect("invalid blinding key");
+	let script_pubkey: Script = match matches.value_of("script-pubkey") {
+		Some(_) => hex_arg("script-pubkey").into(),
+		None => Script::new(),
+	};
+
+	let txout = TxOut {
+		asset: confidential::Asset::Confidential(asset),
+		value: confidential::Value::Confidential(value),
+		nonce: confidential::Nonce::Confidential(nonce),
+		script_pubkey: script_pubkey,
+		witness: TxOutWitness::default(),
+	};
+	let witness = TxOutWitness {
+		surjection_proof: None,
+		rangeproof: Some(Box::new(rangeproof)),
+	};
+
+	let secrets = hal_elements::confidential::unblind_txout(&txout, &witness, &blinding_key)
+		.unwrap_or_else(|e| panic!("{}", e));
+	let network = cmd::network(matches);
+	let info = hal_elements::GetInfo::get_info(&secrets, network);
+	cmd::print_output_network(matches, &info, network)
+}
+
+fn cmd_verify_rangeproof<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("verify-rangeproof", "verify the rangeproof of a value commitment").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::opt("commitment", "the value commitment in hex").takes_value(true).required(true),
+		cmd::opt("rangeproof", "the rangeproof in hex").takes_value(true).required(true),
+		cmd::opt("generator", "the asset generator in hex").takes_value(true).required(true),
+		cmd::opt("script-pubkey", "the scriptPubKey of the output in hex, if not empty")
+			.takes_value(true)
+			.required(false),
+	])
+}
+
+/// The result of verifying a rangeproof.
+#[derive(Serialize)]
+struct RangeProofVerifyInfo {
+	valid: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	min_value: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	max_value: Option<u64>,
+}
+
+fn exec_verify_rangeproof<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_arg = |name: &str| -> Vec<u8> {
+		hex::decode(matches.value_of(name).unwrap()).expect(&format!("invalid {} hex", name))
+	};
+
+	let commitment =
+		PedersenCommitment::from_slice(&hex_arg("commitment")).expect("invalid value commitment");
+	let rangeproof = RangeProof::from_slice(&hex_arg("rangeproof")).expect("invalid rangeproof");
+	let generator = Generator::from_slice(&hex_arg("generator")).expect("invalid generator");
+	// Elements commits to the scriptPubKey in the rangeproof.
+	let script_pubkey = match matches.value_of("script-pubkey") {
+		Some(_) => hex_arg("script-pubkey"),
+		None => Vec::new(),
+	};
+
+	let secp = secp256k1_zkp::Secp256k1::verification_only();
+	let info = match rangeproof.verify(&secp, commitment, &script_pubkey, generator) {
+		// The range is end-exclusive.
+		Ok(range) => RangeProofVerifyInfo {
+			valid: true,
+			min_value: Some(range.start),
+			max_value: Some(range.end - 1),
+		},
+		Err(_) => RangeProofVerifyInfo {
+			valid: false,
+			min_value: None,
+			max_value: None,
+		},
+	};
+	cmd::print_output(matches, &info)
+}
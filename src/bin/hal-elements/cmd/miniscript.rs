@@ -1,13 +1,15 @@
+use elements::encode::VarInt;
 use elements::hashes::hex::FromHex;
 use elements::{self, Script};
 use bitcoin;
 use clap;
 use hal_elements::miniscript::{
-	DescriptorInfo, MiniscriptInfo, MiniscriptKeyType, Miniscripts, PolicyInfo, ScriptContexts,
+	CompilationInfo, DescriptorInfo, LiftInfo, MiniscriptInfo, MiniscriptKeyType, Miniscripts,
+	PolicyInfo, ScriptContexts, SpendingPathInfo, SpendingPathsInfo,
 };
 
 use miniscriptlib;
-use miniscriptlib::descriptor::Descriptor;
+use miniscriptlib::descriptor::{Descriptor, DescriptorPublicKey};
 use miniscriptlib::miniscript::{BareCtx, Legacy, Miniscript, Segwitv0};
 use miniscriptlib::policy::Liftable;
 use miniscriptlib::{policy, MiniscriptKey};
@@ -17,25 +19,121 @@ use cmd;
 pub fn subcommand<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand_group("miniscript", "work with miniscript (alias: ms)")
 		.alias("ms")
+		.subcommand(cmd_address())
+		.subcommand(cmd_compile())
 		.subcommand(cmd_descriptor())
 		.subcommand(cmd_inspect())
+		.subcommand(cmd_lift())
 		.subcommand(cmd_parse())
+		.subcommand(cmd_paths())
 		.subcommand(cmd_policy())
 }
 
 pub fn execute<'a>(matches: &clap::ArgMatches<'a>) {
 	match matches.subcommand() {
+		("address", Some(ref m)) => exec_address(&m),
+		("compile", Some(ref m)) => exec_compile(&m),
 		("descriptor", Some(ref m)) => exec_descriptor(&m),
 		("inspect", Some(ref m)) => exec_inspect(&m),
+		("lift", Some(ref m)) => exec_lift(&m),
 		("parse", Some(ref m)) => exec_parse(&m),
+		("paths", Some(ref m)) => exec_paths(&m),
 		("policy", Some(ref m)) => exec_policy(&m),
 		(_, _) => unreachable!("clap prints help"),
 	};
 }
 
+fn cmd_address<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("address", "get the address of an output descriptor")
+		.args(&cmd::opts_networks())
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::arg("descriptor", "the output descriptor").required(true),
+			cmd::opt("index", "the child index to derive ranged descriptors at")
+				.takes_value(true)
+				.required(false),
+			cmd::opt("blinder", "a blinding pubkey in hex to make the address confidential")
+				.takes_value(true)
+				.required(false),
+		])
+}
+
+fn exec_address<'a>(matches: &clap::ArgMatches<'a>) {
+	let desc_str = matches.value_of("descriptor").expect("no descriptor argument given");
+	let network = cmd::network(matches);
+	let index = matches
+		.value_of("index")
+		.map(|i| i.parse::<u32>().expect("invalid index"))
+		.unwrap_or(0);
+
+	let desc = desc_str.parse::<Descriptor<DescriptorPublicKey>>().expect("invalid descriptor");
+	if !desc.has_wildcard() && matches.is_present("index") {
+		warn!("Descriptor is not ranged, the index is ignored.");
+	}
+	let derived = desc.at_derivation_index(index).expect("invalid derivation index");
+	let mut address =
+		derived.address(network.address_params()).expect("descriptor has no address");
+	address.blinding_pubkey = matches.value_of("blinder").map(cmd::address::parse_blinder);
+
+	let info = cmd::address::address_info(&address);
+	cmd::print_output(matches, &cmd::address::AddressOutput {
+		address: address,
+		info: info,
+	})
+}
+
+fn cmd_compile<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("compile", "compile a policy into a segwit v0 miniscript")
+		.args(&[cmd::opt_yaml(), cmd::opt_cbor()])
+		.args(&[cmd::arg("policy", "the concrete policy to compile").required(true)])
+}
+
+fn compile_policy<Pk: MiniscriptKey>(
+	policy_str: &str,
+	key_type: MiniscriptKeyType,
+) -> Result<CompilationInfo, miniscriptlib::Error>
+where
+	Pk: std::str::FromStr,
+	<Pk as ::std::str::FromStr>::Err: ::std::fmt::Display,
+	Pk::Sha256: std::str::FromStr,
+	<<Pk as miniscriptlib::MiniscriptKey>::Sha256 as ::std::str::FromStr>::Err: ::std::fmt::Display,
+	Pk::Hash160: std::str::FromStr,
+	<<Pk as miniscriptlib::MiniscriptKey>::Hash160 as ::std::str::FromStr>::Err: ::std::fmt::Display,
+	Pk::Hash256: std::str::FromStr,
+	<<Pk as miniscriptlib::MiniscriptKey>::Hash256 as ::std::str::FromStr>::Err: ::std::fmt::Display,
+	Pk::Ripemd160: std::str::FromStr,
+	<<Pk as miniscriptlib::MiniscriptKey>::Ripemd160 as ::std::str::FromStr>::Err: ::std::fmt::Display,
+{
+	let policy: policy::Concrete<Pk> = policy_str.parse()?;
+	let ms = policy.compile::<Segwitv0>()?;
+	let desc = Descriptor::new_wsh(ms.clone())?;
+	Ok(CompilationInfo {
+		key_type: key_type,
+		miniscript: ms.to_string(),
+		descriptor: desc.to_string(),
+		max_satisfaction_weight: desc.max_weight_to_satisfy().ok(),
+	})
+}
+
+fn exec_compile<'a>(matches: &clap::ArgMatches<'a>) {
+	let policy_str = matches.value_of("policy").expect("no policy argument given");
+
+	// First try with pubkeys, then with strings.
+	let info = compile_policy::<bitcoin::PublicKey>(policy_str, MiniscriptKeyType::PublicKey)
+		.or_else(|e| {
+			debug!("Can't compile policy with public keys: {}", e);
+			compile_policy::<String>(policy_str, MiniscriptKeyType::String)
+		});
+	match info {
+		Ok(info) => cmd::print_output(matches, &info),
+		Err(e) => panic!("Failed to compile policy: {}", e),
+	}
+}
+
 fn cmd_descriptor<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("descriptor", "get information about an output descriptor")
-		.arg(cmd::opt_yaml())
+		.args(&[cmd::opt_yaml(), cmd::opt_cbor()])
 		.args(&[cmd::arg("descriptor", "the output descriptor to inspect").required(true)])
 }
 
@@ -53,7 +151,8 @@ fn exec_descriptor<'a>(matches: &clap::ArgMatches<'a>) {
 			witness_script: desc.explicit_script().ok().map(|x| x.as_bytes().into()),
 			max_satisfaction_weight: desc.max_weight_to_satisfy().ok(),
 			policy: policy::Liftable::lift(&desc).map(|pol| pol.to_string()).ok(),
-		    descriptor_info: Some(desc.desc_type().to_string()),
+			descriptor_info: Some(desc.desc_type().to_string()),
+			sane: desc.sanity_check().is_ok(),
 		})
 		.or_else(|e| {
 			debug!("Can't parse descriptor with public keys: {}", e);
@@ -66,7 +165,8 @@ fn exec_descriptor<'a>(matches: &clap::ArgMatches<'a>) {
 				witness_script: None,
 				max_satisfaction_weight: desc.max_weight_to_satisfy().ok(),
 				policy: policy::Liftable::lift(&desc).map(|pol| pol.to_string()).ok(),
-			    descriptor_info: Some(desc.desc_type().to_string()),
+				descriptor_info: Some(desc.desc_type().to_string()),
+				sane: desc.sanity_check().is_ok(),
 			})
 		})
 		.expect("invalid miniscript");
@@ -74,11 +174,11 @@ fn exec_descriptor<'a>(matches: &clap::ArgMatches<'a>) {
 }
 
 fn cmd_inspect<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("inspect", "inspect miniscripts").arg(cmd::opt_yaml()).args(&[cmd::arg(
-		"miniscript",
-		"the miniscript to inspect",
-	)
-	.required(true)])
+	cmd::subcommand("inspect", "inspect miniscripts").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::arg("miniscript", "the miniscript to inspect").required(true),
+	])
 }
 
 fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
@@ -130,9 +230,51 @@ fn exec_inspect<'a>(matches: &clap::ArgMatches<'a>) {
 	cmd::print_output(matches, &info);
 }
 
+fn cmd_lift<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("lift", "lift a script into a miniscript and its abstract policy")
+		.args(&[cmd::opt_yaml(), cmd::opt_cbor()])
+		.args(&[cmd::arg("script", "the script in hex").required(true)])
+}
+
+fn exec_lift<'a>(matches: &clap::ArgMatches<'a>) {
+	let script_hex = matches.value_of("script").expect("no script argument given");
+	let script = Script::from(Vec::<u8>::from_hex(&script_hex).expect("invalid hex script"));
+
+	let segwit = Miniscript::<bitcoin::PublicKey, Segwitv0>::parse_insane(&script)
+		.map_err(|e| info!("Cannot parse as segwit Miniscript {}", e))
+		.ok();
+	let legacy = Miniscript::<bitcoin::PublicKey, Legacy>::parse_insane(&script)
+		.map_err(|e| debug!("Cannot parse as Legacy Miniscript {}", e))
+		.ok();
+	let bare = Miniscript::<bitcoin::PublicKey, BareCtx>::parse_insane(&script)
+		.map_err(|e| debug!("Cannot parse as Bare Miniscript {}", e))
+		.ok();
+	if segwit.is_none() && legacy.is_none() && bare.is_none() {
+		panic!("Script is not a valid Miniscript under any script context")
+	}
+
+	// The abstract policy doesn't depend on the script context.
+	let policy = segwit.as_ref().map(|ms| ms.lift())
+		.or(legacy.as_ref().map(|ms| ms.lift()))
+		.or(bare.as_ref().map(|ms| ms.lift()))
+		.unwrap()
+		.map_err(|e| info!("Lift error {}", e))
+		.ok();
+
+	let info = LiftInfo {
+		miniscript: Miniscripts {
+			bare: bare.map(|ms| ms.to_string()),
+			p2sh: legacy.map(|ms| ms.to_string()),
+			segwitv0: segwit.map(|ms| ms.to_string()),
+		},
+		policy: policy.map(|p| p.to_string()),
+	};
+	cmd::print_output(matches, &info);
+}
+
 fn cmd_parse<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("parse", "parse a script into a miniscript")
-		.arg(cmd::opt_yaml())
+		.args(&[cmd::opt_yaml(), cmd::opt_cbor()])
 		.args(&[cmd::arg("script", "hex script to ").required(true)])
 }
 
@@ -164,12 +306,126 @@ fn exec_parse<'a>(matches: &clap::ArgMatches<'a>) {
 	cmd::print_output(matches, &comb_info);
 }
 
+fn cmd_paths<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("paths", "list the ways to satisfy an output descriptor")
+		.args(&[cmd::opt_yaml(), cmd::opt_cbor()])
+		.args(&[cmd::arg("descriptor", "the output descriptor to analyze").required(true)])
+}
+
+/// All combinations of `k` elements of `items`.
+fn combinations<T: Clone>(items: &[T], k: usize) -> Vec<Vec<T>> {
+	if k == 0 {
+		return vec![vec![]];
+	}
+	if items.len() < k {
+		return vec![];
+	}
+	let mut ret = combinations(&items[1..], k - 1);
+	for comb in ret.iter_mut() {
+		comb.insert(0, items[0].clone());
+	}
+	ret.extend(combinations(&items[1..], k));
+	ret
+}
+
+/// Enumerate the satisfaction paths of the semantic policy.
+///
+/// Each path is the list of leaf conditions that together satisfy the policy.
+fn satisfaction_paths<Pk: MiniscriptKey>(
+	policy: &policy::Semantic<Pk>,
+) -> Vec<Vec<policy::Semantic<Pk>>> {
+	match *policy {
+		policy::Semantic::Unsatisfiable => vec![],
+		policy::Semantic::Trivial => vec![vec![]],
+		policy::Semantic::Threshold(k, ref subs) => {
+			let sub_paths = subs.iter().map(satisfaction_paths).collect::<Vec<_>>();
+			let mut ret = Vec::new();
+			for comb in combinations(&sub_paths, k) {
+				// Take the cartesian product of the paths of the chosen subpolicies.
+				let mut product = vec![vec![]];
+				for paths in comb {
+					product = product
+						.iter()
+						.flat_map(|prefix: &Vec<_>| {
+							paths.iter().map(move |path| {
+								let mut p = prefix.clone();
+								p.extend(path.iter().cloned());
+								p
+							})
+						})
+						.collect();
+				}
+				ret.extend(product);
+			}
+			ret
+		}
+		ref leaf => vec![vec![leaf.clone()]],
+	}
+}
+
+fn get_paths_info<Pk: MiniscriptKey>(
+	policy: policy::Semantic<Pk>,
+	key_type: MiniscriptKeyType,
+) -> SpendingPathsInfo {
+	let policy = policy.normalized();
+	let paths = satisfaction_paths(&policy)
+		.into_iter()
+		.map(|path| {
+			let mut keys = Vec::new();
+			let mut hashes = Vec::new();
+			for leaf in path.iter() {
+				match *leaf {
+					policy::Semantic::Key(ref pk) => keys.push(pk.to_string()),
+					_ if leaf.relative_timelocks().is_empty()
+						&& leaf.absolute_timelocks().is_empty() =>
+					{
+						hashes.push(leaf.to_string())
+					}
+					_ => {}
+				}
+			}
+			let conjunction = policy::Semantic::Threshold(path.len(), path);
+			SpendingPathInfo {
+				keys: keys,
+				hashes: hashes,
+				relative_timelocks: conjunction.relative_timelocks(),
+				absolute_timelocks: conjunction.absolute_timelocks(),
+			}
+		})
+		.collect();
+	SpendingPathsInfo {
+		key_type: key_type,
+		policy: policy.to_string(),
+		paths: paths,
+	}
+}
+
+fn exec_paths<'a>(matches: &clap::ArgMatches<'a>) {
+	let desc_str = matches.value_of("descriptor").expect("no descriptor argument given");
+
+	let info = desc_str
+		.parse::<Descriptor<bitcoin::PublicKey>>()
+		.map_err(|e| e.to_string())
+		.and_then(|desc| policy::Liftable::lift(&desc).map_err(|e| e.to_string()))
+		.map(|pol| get_paths_info(pol, MiniscriptKeyType::PublicKey))
+		.or_else(|e| {
+			debug!("Can't analyze descriptor with public keys: {}", e);
+			// Then try with strings.
+			desc_str
+				.parse::<Descriptor<String>>()
+				.and_then(|desc| policy::Liftable::lift(&desc))
+				.map(|pol| get_paths_info(pol, MiniscriptKeyType::String))
+		})
+		.expect("invalid descriptor");
+	cmd::print_output(matches, &info);
+}
+
 fn cmd_policy<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("policy", "inspect policies").arg(cmd::opt_yaml()).args(&[cmd::arg(
-		"policy",
-		"the miniscript policy to inspect",
-	)
-	.required(true)])
+	cmd::subcommand("policy", "inspect policies").args(&[
+		cmd::opt_yaml(),
+		cmd::opt_cbor(),
+		cmd::arg("policy", "the miniscript policy to inspect").required(true),
+	])
 }
 
 fn get_policy_info<Pk: MiniscriptKey>(
@@ -278,6 +534,9 @@ impl FromScriptContexts for MiniscriptInfo {
 			max_satisfaction_witness_elements: ms.max_satisfaction_witness_elements().ok(),
 			max_satisfaction_size_segwit: None,
 			max_satisfaction_size_non_segwit: ms.max_satisfaction_size().ok(),
+			// The satisfaction is in the scriptSig, which counts 4 weight units per byte.
+			max_satisfaction_weight_segwit: None,
+			max_satisfaction_weight_non_segwit: ms.max_satisfaction_size().ok().map(|s| s * 4),
 			script: script.map(|x| x.into_bytes().into()),
 			policy: match ms.lift() {
 				Ok(pol) => Some(pol.to_string()),
@@ -307,6 +566,9 @@ impl FromScriptContexts for MiniscriptInfo {
 			max_satisfaction_witness_elements: ms.max_satisfaction_witness_elements().ok(),
 			max_satisfaction_size_segwit: None,
 			max_satisfaction_size_non_segwit: ms.max_satisfaction_size().ok(),
+			// The satisfaction is in the scriptSig, which counts 4 weight units per byte.
+			max_satisfaction_weight_segwit: None,
+			max_satisfaction_weight_non_segwit: ms.max_satisfaction_size().ok().map(|s| s * 4),
 			script: script.map(|x| x.into_bytes().into()),
 			policy: match ms.lift() {
 				Ok(pol) => Some(pol.to_string()),
@@ -336,6 +598,16 @@ impl FromScriptContexts for MiniscriptInfo {
 			max_satisfaction_witness_elements: ms.max_satisfaction_witness_elements().ok(),
 			max_satisfaction_size_segwit: ms.max_satisfaction_size().ok(),
 			max_satisfaction_size_non_segwit: None,
+			// The satisfaction is in the witness, which counts 1 weight unit per byte, and
+			// is prefixed by the number of witness elements.
+			max_satisfaction_weight_segwit: match (
+				ms.max_satisfaction_size(),
+				ms.max_satisfaction_witness_elements(),
+			) {
+				(Ok(size), Ok(n)) => Some(size + VarInt(n as u64).len()),
+				_ => None,
+			},
+			max_satisfaction_weight_non_segwit: None,
 			script: script.map(|x| x.into_bytes().into()),
 			policy: match ms.lift() {
 				Ok(pol) => Some(pol.to_string()),
@@ -378,6 +650,12 @@ impl FromScriptContexts for MiniscriptInfo {
 					max_satisfaction_size_non_segwit: a
 						.max_satisfaction_size_non_segwit
 						.or(b.max_satisfaction_size_non_segwit),
+					max_satisfaction_weight_segwit: a
+						.max_satisfaction_weight_segwit
+						.or(b.max_satisfaction_weight_segwit),
+					max_satisfaction_weight_non_segwit: a
+						.max_satisfaction_weight_non_segwit
+						.or(b.max_satisfaction_weight_non_segwit),
 					script: a.script,
 					policy: a.policy.or(b.policy),
 					requires_sig: a.requires_sig,
@@ -1,9 +1,16 @@
 pub mod address;
 pub mod block;
+pub mod confidential;
 pub mod tx;
 pub mod miniscript;
 pub mod pset;
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+
+use elements::{AssetId, BlockHash};
 use hal_elements::Network;
 
 /// Build a list of all built-in subcommands.
@@ -11,6 +18,7 @@ pub fn subcommands<'a>() -> Vec<clap::App<'a, 'a>> {
 	vec![
 		address::subcommand(),
 		block::subcommand(),
+		confidential::subcommand(),
 		tx::subcommand(),
 		miniscript::subcommand(),
 		pset::subcommand(),
@@ -47,30 +55,121 @@ pub fn subcommand<'a>(name: &'static str, about: &'static str) -> clap::App<'a,
 		.setting(clap::AppSettings::DisableHelpSubcommand)
 }
 
+/// Get the value of a positional argument, reading it from stdin if it is `-` or omitted.
+pub fn arg_or_stdin<'a>(matches: &'a clap::ArgMatches<'a>, arg: &str) -> Cow<'a, str> {
+	match matches.value_of(arg) {
+		Some(s) if s != "-" => s.into(),
+		_ => {
+			let mut input = String::new();
+			io::stdin().read_to_string(&mut input).expect("failed to read from stdin");
+			input.trim().to_owned().into()
+		}
+	}
+}
+
 pub fn opts_networks<'a>() -> Vec<clap::Arg<'a, 'a>> {
+	let mut opts = opts_networks_no_short();
+	let regtest = opts.remove(0).short("r");
+	opts.insert(0, regtest);
+	opts
+}
+
+/// Like [opts_networks], but without `-r` for elementsregtest, for commands that use it
+/// for `--raw-stdout`.
+pub fn opts_networks_no_short<'a>() -> Vec<clap::Arg<'a, 'a>> {
 	vec![
 		clap::Arg::with_name("elementsregtest")
 			.long("elementsregtest")
-			.short("r")
 			.help("run in elementsregtest mode")
 			.takes_value(false)
 			.required(false),
 		clap::Arg::with_name("liquid")
 			.long("liquid")
+			.alias("mainnet")
 			.help("run in liquid mode")
 			.takes_value(false)
 			.required(false),
+		clap::Arg::with_name("liquidtestnet")
+			.long("liquidtestnet")
+			.alias("testnet")
+			.help("run in liquid testnet mode")
+			.takes_value(false)
+			.required(false),
+		clap::Arg::with_name("genesis-hash")
+			.long("genesis-hash")
+			.help("the genesis block hash of the chain, needed for taproot sighashes")
+			.takes_value(true)
+			.required(false),
+		clap::Arg::with_name("bech-hrp")
+			.long("bech-hrp")
+			.help("the HRP of unconfidential segwit addresses of a custom chain")
+			.takes_value(true)
+			.required(false),
+		clap::Arg::with_name("blech-hrp")
+			.long("blech-hrp")
+			.help("the HRP of confidential segwit addresses of a custom chain")
+			.takes_value(true)
+			.required(false),
+		clap::Arg::with_name("policy-asset")
+			.long("policy-asset")
+			.help("the policy asset id of a custom chain")
+			.takes_value(true)
+			.required(false),
 	]
 }
 
+/// Get the network from the flags given with [opts_networks].
+///
+/// If any of the parameters of the network is overridden, a [Network::Custom] is returned
+/// with the other parameters taken from the selected network.
 pub fn network<'a>(matches: &clap::ArgMatches<'a>) -> Network {
-	if matches.is_present("elementsregtest") {
+	let network = if matches.is_present("elementsregtest") {
 		Network::ElementsRegtest
 	} else if matches.is_present("liquid") {
 		Network::Liquid
+	} else if matches.is_present("liquidtestnet") {
+		Network::LiquidTestnet
 	} else {
 		Network::ElementsRegtest
+	};
+
+	let custom = ["genesis-hash", "bech-hrp", "blech-hrp", "policy-asset"];
+	if !custom.iter().any(|o| matches.is_present(o)) {
+		return network;
+	}
+
+	// Addresses need static params, so the custom network lives until the process exits.
+	let leak = |s: &str| -> &'static str { Box::leak(s.to_owned().into_boxed_str()) };
+	let mut params = network.address_params().clone();
+	if let Some(hrp) = matches.value_of("bech-hrp") {
+		params.bech_hrp = leak(hrp);
 	}
+	if let Some(hrp) = matches.value_of("blech-hrp") {
+		params.blech_hrp = leak(hrp);
+	}
+	let custom = hal_elements::CustomNetwork {
+		address_params: params,
+		genesis_hash: match matches.value_of("genesis-hash") {
+			Some(h) => Some(h.parse::<BlockHash>().expect("invalid genesis hash")),
+			None => network.genesis_hash(),
+		},
+		policy_asset: match matches.value_of("policy-asset") {
+			Some(a) => Some(a.parse::<AssetId>().expect("invalid policy asset id")),
+			None => network.policy_asset(),
+		},
+	};
+	if custom.address_params == *network.address_params()
+		&& custom.genesis_hash == network.genesis_hash()
+		&& custom.policy_asset == network.policy_asset()
+	{
+		return network;
+	}
+	Network::Custom(Box::leak(Box::new(custom)))
+}
+
+/// Get the genesis hash of the network, which is committed to by taproot sighashes.
+pub fn genesis_hash<'a>(matches: &clap::ArgMatches<'a>) -> BlockHash {
+	network(matches).genesis_hash().expect("--genesis-hash is required for taproot inputs")
 }
 
 pub fn opt_yaml<'a>() -> clap::Arg<'a, 'a> {
@@ -82,9 +181,164 @@ pub fn opt_yaml<'a>() -> clap::Arg<'a, 'a> {
 		.required(false)
 }
 
+pub fn opt_cbor<'a>() -> clap::Arg<'a, 'a> {
+	clap::Arg::with_name("cbor")
+		.long("cbor")
+		.help("print output as raw CBOR bytes instead of JSON")
+		.takes_value(false)
+		.required(false)
+		.conflicts_with("yaml")
+}
+
+/// Options to control the indentation of JSON output, for all commands.
+pub fn opts_indent<'a>() -> Vec<clap::Arg<'a, 'a>> {
+	vec![
+		clap::Arg::with_name("indent")
+			.long("indent")
+			.help("the number of spaces to indent JSON output with")
+			.takes_value(true)
+			.required(false)
+			.global(true),
+		clap::Arg::with_name("tabs")
+			.long("tabs")
+			.help("indent JSON output with tabs")
+			.takes_value(false)
+			.required(false)
+			.conflicts_with("indent")
+			.global(true),
+	]
+}
+
+pub fn opt_asset_labels<'a>() -> clap::Arg<'a, 'a> {
+	clap::Arg::with_name("asset-labels")
+		.long("asset-labels")
+		.help("a JSON file mapping asset ids to labels")
+		.takes_value(true)
+		.required(false)
+}
+
+/// Read the asset labels from the file given with `--asset-labels`, if any.
+fn asset_labels<'a>(matches: &clap::ArgMatches<'a>) -> Option<BTreeMap<AssetId, String>> {
+	let path = matches.value_of("asset-labels")?;
+	let file = File::open(path).expect("failed to open asset labels file");
+	let labels: BTreeMap<String, String> =
+		serde_json::from_reader(file).expect("invalid asset labels JSON");
+	Some(labels.into_iter().map(|(id, label)| {
+		(id.parse::<AssetId>().expect(&format!("invalid asset id: {}", id)), label)
+	}).collect())
+}
+
+/// Format an amount in satoshi in whole coins with 8 decimals.
+fn btc_amount(sat: u64) -> serde_json::Value {
+	format!("{}.{:08}", sat / 100_000_000, sat % 100_000_000).into()
+}
+
+/// Annotate every object in the output.
+///
+/// Objects with an `asset` that has a custom label get that label. If `btc_asset` is given,
+/// explicit values and amounts next to an `asset` that is `btc_asset` also get a `value_btc`
+/// in whole coins.
+fn annotate(
+	value: &mut serde_json::Value,
+	labels: Option<&BTreeMap<AssetId, String>>,
+	btc_asset: Option<AssetId>,
+) {
+	match *value {
+		serde_json::Value::Object(ref mut map) => {
+			let label = map.get("asset")
+				.and_then(|a| a.as_str())
+				.and_then(|a| a.parse::<AssetId>().ok())
+				.and_then(|a| labels.and_then(|l| l.get(&a)));
+			if let Some(label) = label {
+				map.insert("label".to_owned(), label.clone().into());
+			}
+
+			// The asset is either a plain id or an asset info object.
+			let asset = map.get("asset")
+				.and_then(|a| a.as_str().or_else(|| a.get("asset").and_then(|a| a.as_str())))
+				.and_then(|a| a.parse::<AssetId>().ok());
+			if btc_asset.is_some() && asset == btc_asset {
+				if let Some(sat) = map.get("value").and_then(|v| v.as_u64()) {
+					map.insert("value_btc".to_owned(), btc_amount(sat));
+				}
+				for key in &["value", "amount"] {
+					if let Some(&mut serde_json::Value::Object(ref mut info)) = map.get_mut(*key) {
+						let explicit = info.get("type").and_then(|t| t.as_str()) == Some("explicit");
+						match info.get("value").and_then(|v| v.as_u64()) {
+							Some(sat) if explicit => {
+								info.insert("value_btc".to_owned(), btc_amount(sat));
+							}
+							_ => {}
+						}
+					}
+				}
+			}
+			for v in map.values_mut() {
+				annotate(v, labels, btc_asset);
+			}
+		}
+		serde_json::Value::Array(ref mut values) => {
+			for v in values.iter_mut() {
+				annotate(v, labels, btc_asset);
+			}
+		}
+		_ => {}
+	}
+}
+
 pub fn print_output<'a, T: serde::Serialize>(matches: &clap::ArgMatches<'a>, out: &T) {
+	print_annotated(matches, out, None)
+}
+
+/// Like [print_output], but with `--btc-amounts` also showing the values of the policy asset
+/// of the network in whole coins.
+pub fn print_output_network<'a, T: serde::Serialize>(
+	matches: &clap::ArgMatches<'a>,
+	out: &T,
+	network: Network,
+) {
+	print_annotated(matches, out, Some(network))
+}
+
+fn print_annotated<'a, T: serde::Serialize>(
+	matches: &clap::ArgMatches<'a>,
+	out: &T,
+	network: Option<Network>,
+) {
+	let labels = asset_labels(matches);
+	let btc_asset = match network {
+		Some(network) if matches.is_present("btc-amounts") => {
+			let asset = network.policy_asset();
+			if asset.is_none() {
+				warn!("The policy asset of network {} is not known, use --policy-asset.",
+					network.as_str());
+			}
+			asset
+		}
+		_ => None,
+	};
+	if labels.is_none() && btc_asset.is_none() {
+		return write_output(matches, out);
+	}
+
+	let mut value = serde_json::to_value(out).expect("output can be converted to JSON");
+	annotate(&mut value, labels.as_ref(), btc_asset);
+	write_output(matches, &value);
+}
+
+fn write_output<'a, T: serde::Serialize>(matches: &clap::ArgMatches<'a>, out: &T) {
 	if matches.is_present("yaml") {
 		serde_yaml::to_writer(::std::io::stdout(), &out).unwrap();
+	} else if matches.is_present("cbor") {
+		serde_cbor::to_writer(::std::io::stdout(), &out).unwrap();
+	} else if matches.is_present("indent") || matches.is_present("tabs") {
+		let indent = match matches.value_of("indent") {
+			Some(n) => vec![b' '; n.parse::<usize>().expect("invalid indent")],
+			None => vec![b'\t'],
+		};
+		let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+		let mut serializer = serde_json::Serializer::with_formatter(::std::io::stdout(), formatter);
+		out.serialize(&mut serializer).unwrap();
 	} else {
 		serde_json::to_writer_pretty(::std::io::stdout(), &out).unwrap();
 	}
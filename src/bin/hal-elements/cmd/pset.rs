@@ -1,5 +1,7 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::str::FromStr;
 
 use base64;
@@ -8,33 +10,63 @@ use elements::hashes::Hash;
 use hex;
 
 use elements::{secp256k1_zkp, BlockHash};
+use elements::opcodes;
+use elements::script::{Builder, Instruction};
+use elements::{PubkeyHash, Script, ScriptHash, WPubkeyHash};
 use bitcoin::bip32;
-use elements::{pset, Transaction};
+use elements::{confidential, pset, slip77, AssetId, OutPoint, Transaction, TxOutSecrets, Txid};
 use elements::pset::{PartiallySignedTransaction as Pset, PsbtSighashType};
+use elements::schnorr::{SchnorrSig, TapTweak};
+use elements::SchnorrSighashType;
 use bitcoin::{self, PrivateKey, PublicKey};
 use elements::encode::{serialize, deserialize};
+use serde::Serialize;
 
 use cmd;
+use hal_elements::confidential::TxOutSecretsInfo;
+use miniscriptlib::descriptor::{Descriptor, DescriptorPublicKey};
 use miniscriptlib::psbt::PsbtExt;
 
 pub fn subcommand<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand_group("pset", "partially signed Elements transactions")
+		.subcommand(cmd_add_input())
+		.subcommand(cmd_add_output())
+		.subcommand(cmd_blind())
+		.subcommand(cmd_bump_fee())
 		.subcommand(cmd_create())
 		.subcommand(cmd_decode())
 		.subcommand(cmd_edit())
+		.subcommand(cmd_extract())
 		.subcommand(cmd_finalize())
+		.subcommand(cmd_is_final())
+		.subcommand(cmd_join())
 		.subcommand(cmd_merge())
 		.subcommand(cmd_rawsign())
+		.subcommand(cmd_rawsign_all())
+		.subcommand(cmd_sighash())
+		.subcommand(cmd_sign_with_descriptor())
+		.subcommand(cmd_unblind())
 }
 
 pub fn execute<'a>(matches: &clap::ArgMatches<'a>) {
 	match matches.subcommand() {
+		("add-input", Some(ref m)) => exec_add_input(&m),
+		("add-output", Some(ref m)) => exec_add_output(&m),
+		("blind", Some(ref m)) => exec_blind(&m),
+		("bump-fee", Some(ref m)) => exec_bump_fee(&m),
 		("create", Some(ref m)) => exec_create(&m),
 		("decode", Some(ref m)) => exec_decode(&m),
 		("edit", Some(ref m)) => exec_edit(&m),
+		("extract", Some(ref m)) => exec_extract(&m),
 		("finalize", Some(ref m)) => exec_finalize(&m),
+		("is-final", Some(ref m)) => exec_is_final(&m),
+		("join", Some(ref m)) => exec_join(&m),
 		("merge", Some(ref m)) => exec_merge(&m),
 		("rawsign", Some(ref m)) => exec_rawsign(&m),
+		("rawsign-all", Some(ref m)) => exec_rawsign_all(&m),
+		("sighash", Some(ref m)) => exec_sighash(&m),
+		("sign-with-descriptor", Some(ref m)) => exec_sign_with_descriptor(&m),
+		("unblind", Some(ref m)) => exec_unblind(&m),
 		(c, _) => eprintln!("command {} unknown", c),
 	};
 }
@@ -44,29 +76,344 @@ enum PsetSource {
 	Base64,
 	Hex,
 	File,
+	/// Raw bytes read from stdin with `--raw-stdin`.
+	Stdin,
+}
+
+impl PsetSource {
+	fn description(&self) -> &'static str {
+		match *self {
+			PsetSource::Base64 => "base64",
+			PsetSource::Hex => "hex",
+			PsetSource::File => "a file path",
+			PsetSource::Stdin => "raw bytes on stdin",
+		}
+	}
+}
+
+/// Tell the user on stderr how the PSET argument was parsed, if `--print-source` is given.
+fn print_source<'a>(matches: &clap::ArgMatches<'a>, source: &PsetSource) {
+	if matches.is_present("print-source") {
+		eprintln!("PSET was read as {}", source.description());
+	}
 }
 
 /// Tries to decode the string as hex and base64, if it works, returns the bytes.
 /// If not, tries to open a filename with the given string as relative path, if it works, returns
 /// the content bytes.
+/// If the string is `-`, the PSET is read from stdin in hex or base64.
 /// Also returns an enum value indicating which source worked.
-fn file_or_raw(flag: &str) -> (Vec<u8>, PsetSource) {
+fn file_or_raw(flag: &str) -> Result<(Vec<u8>, PsetSource), String> {
+	if flag == "-" {
+		let mut input = String::new();
+		io::stdin().read_to_string(&mut input).map_err(|e| format!("error reading stdin: {}", e))?;
+		let input = input.trim();
+		if let Ok(raw) = hex::decode(input) {
+			return Ok((raw, PsetSource::Hex));
+		}
+		return base64::decode(input)
+			.map(|raw| (raw, PsetSource::Base64))
+			.map_err(|_| "invalid hex or base64 on stdin".to_owned());
+	}
 	if let Ok(raw) = hex::decode(&flag) {
-		(raw, PsetSource::Hex)
-	} else if let Ok(raw) = base64::decode(&flag) {
-		(raw, PsetSource::Base64)
-	} else if let Ok(mut file) = File::open(&flag) {
-		let mut buf = Vec::new();
-		file.read_to_end(&mut buf).expect("error reading file");
-		(buf, PsetSource::File)
+		return Ok((raw, PsetSource::Hex));
+	}
+	if let Ok(raw) = base64::decode(&flag) {
+		return Ok((raw, PsetSource::Base64));
+	}
+
+	match File::open(&flag) {
+		Ok(mut file) => {
+			let mut buf = Vec::new();
+			file.read_to_end(&mut buf).map_err(|e| format!("error reading file {}: {}", flag, e))?;
+			Ok((buf, PsetSource::File))
+		}
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+			if flag.contains('/') || flag.contains(".pset") {
+				Err(format!("file not found: {}", flag))
+			} else {
+				Err("invalid hex, base64 or unknown file".to_owned())
+			}
+		}
+		Err(e) => Err(format!("error opening file {}: {}", flag, e)),
+	}
+}
+
+/// Option to read the PSET as raw bytes from stdin instead of the `pset` argument.
+///
+/// The `pset` argument can then be omitted, or given as `-` when other positional
+/// arguments follow it.
+fn opt_raw_stdin<'a>() -> clap::Arg<'a, 'a> {
+	cmd::opt("raw-stdin", "read the raw bytes of the PSET from stdin, the PSET can be left out or `-`")
+		.required(false)
+}
+
+/// Read all raw bytes from stdin.
+fn read_raw_stdin() -> Result<Vec<u8>, String> {
+	let mut raw = Vec::new();
+	io::stdin().read_to_end(&mut raw).map_err(|e| format!("error reading stdin: {}", e))?;
+	Ok(raw)
+}
+
+/// Read the PSET from stdin as raw bytes if `--raw-stdin` is given, otherwise from the `pset`
+/// argument using [file_or_raw].
+fn pset_input<'a>(matches: &clap::ArgMatches<'a>) -> Result<(Vec<u8>, PsetSource), String> {
+	if matches.is_present("raw-stdin") {
+		if matches.value_of("pset").map(|p| p != "-").unwrap_or(false) {
+			return Err("the PSET must be left out or `-` with --raw-stdin".to_owned());
+		}
+		return Ok((read_raw_stdin()?, PsetSource::Stdin));
+	}
+	file_or_raw(matches.value_of("pset").unwrap())
+}
+
+/// Read the PSETs of the `psets` argument using [file_or_raw], after the one read from stdin as
+/// raw bytes if `--raw-stdin` is given.
+///
+/// Also returns how the result should be written back, which is base64 unless the PSET was read
+/// from stdin as raw bytes.
+fn psets_input<'a>(
+	matches: &clap::ArgMatches<'a>,
+	psets: &[String],
+) -> Result<(Vec<Vec<u8>>, PsetSource), String> {
+	let mut raws = Vec::with_capacity(psets.len() + 1);
+	let mut source = PsetSource::Base64;
+	if matches.is_present("raw-stdin") {
+		if psets.iter().any(|p| p == "-") {
+			return Err("stdin can't be read twice, with `-` and --raw-stdin".to_owned());
+		}
+		raws.push(read_raw_stdin()?);
+		source = PsetSource::Stdin;
+	}
+	for pset in psets {
+		raws.push(file_or_raw(pset)?.0);
+	}
+	Ok((raws, source))
+}
+
+/// Write the PSET to the output file, stdout as raw bytes or back in the format it was read in.
+fn write_pset<'a>(matches: &clap::ArgMatches<'a>, source: PsetSource, pset: &Pset) {
+	let raw = serialize(pset);
+	if let Some(path) = matches.value_of("output") {
+		let mut file = File::create(&path).expect("failed to open output file");
+		file.write_all(&raw).expect("error writing output file");
+	} else if matches.is_present("raw-stdout") {
+		::std::io::stdout().write_all(&raw).unwrap();
 	} else {
-		panic!("Can't load PSET: invalid hex, base64 or unknown file");
+		match source {
+			PsetSource::Hex => print!("{}", hex::encode(&raw)),
+			PsetSource::Base64 => print!("{}", base64::encode(&raw)),
+			PsetSource::File => {
+				let path = matches.value_of("pset").unwrap();
+				let mut file = File::create(&path).expect("failed to PSET file for writing");
+				file.write_all(&raw).expect("error writing PSET file");
+			}
+			PsetSource::Stdin => ::std::io::stdout().write_all(&raw).unwrap(),
+		}
+	}
+}
+
+fn cmd_add_input<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("add-input", "add an input to a PSET").args(&[
+		cmd::arg("pset", "PSET to add the input to, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("txid", "the txid of the output to spend").takes_value(true).required(true),
+		cmd::opt("vout", "the index of the output to spend").takes_value(true).required(true),
+		cmd::opt("sequence", "the sequence number of the input").takes_value(true).required(false),
+		cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+	])
+}
+
+fn exec_add_input<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let txid: Txid = matches.value_of("txid").unwrap().parse().expect("invalid txid");
+	let vout: u32 = matches.value_of("vout").unwrap().parse().expect("invalid vout");
+	let mut input = pset::Input::from_prevout(OutPoint::new(txid, vout));
+	if let Some(seq) = matches.value_of("sequence") {
+		input.sequence = Some(elements::Sequence(seq.parse().expect("invalid sequence")));
 	}
+	pset.add_input(input);
+
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_add_output<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("add-output", "add an explicit output to a PSET").args(&[
+		cmd::arg("pset", "PSET to add the output to, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("asset", "the asset id of the output").takes_value(true).required(true),
+		cmd::opt("value", "the value of the output in satoshi").takes_value(true).required(true),
+		cmd::opt("script-pubkey", "the scriptPubKey of the output in hex")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("blinding-pubkey", "the blinding pubkey to mark the output for blinding")
+			.takes_value(true)
+			.required(false),
+		cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+	])
+}
+
+fn exec_add_output<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let asset: AssetId = matches.value_of("asset").unwrap().parse().expect("invalid asset id");
+	let value: u64 = matches.value_of("value").unwrap().parse().expect("invalid value");
+	let script_pubkey = hex::decode(matches.value_of("script-pubkey").unwrap())
+		.expect("invalid script-pubkey hex");
+	let blinding_pubkey = matches.value_of("blinding-pubkey").map(|pk| {
+		pk.parse::<PublicKey>().expect("invalid blinding pubkey")
+	});
+	let output = pset::Output::new_explicit(script_pubkey.into(), value, asset, blinding_pubkey);
+	pset.add_output(output);
+
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_blind<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("blind", "blind the outputs of a PSET that have a blinding key set").args(&[
+		cmd::arg("pset", "PSET to blind, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("blinding-key", "the blinding secret of a confidential input `<input-idx>:<secret>`")
+			.takes_value(true)
+			.multiple(true)
+			.number_of_values(1)
+			.required(false),
+		cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+	])
+}
+
+/// Parses a `<index>:<secret>` pair of the `--blinding-key` option, where the index is the one
+/// of an input or output as given by `kind`.
+///
+/// The error doesn't contain the secret, so that it's not leaked in logs.
+fn parse_blinding_key_pair(
+	pair_str: &str,
+	kind: &str,
+) -> Result<(usize, secp256k1_zkp::SecretKey), String> {
+	let mut pair = pair_str.splitn(2, ":");
+	let idx_str = pair.next().unwrap();
+	let idx = idx_str.parse::<usize>()
+		.map_err(|_| format!("invalid {} index `{}` in blinding key", kind, idx_str))?;
+	let hex = pair.next()
+		.ok_or_else(|| format!("blinding key of {} {} must be given as `<idx>:<secret>`", kind, idx))?;
+	let sk = secp256k1_zkp::SecretKey::from_str(&hex)
+		.map_err(|e| format!("invalid blinding key secret of {} {}: {}", kind, idx, e))?;
+	Ok((idx, sk))
+}
+
+/// Parses all `--blinding-key` options, see [parse_blinding_key_pair].
+fn blinding_keys<'a>(
+	matches: &clap::ArgMatches<'a>,
+	kind: &str,
+	count: usize,
+) -> HashMap<usize, secp256k1_zkp::SecretKey> {
+	let pairs = match matches.values_of("blinding-key") {
+		Some(pairs) => pairs,
+		None => return HashMap::new(),
+	};
+	pairs.map(|p| {
+		let (idx, sk) = parse_blinding_key_pair(p, kind).unwrap_or_else(|e| panic!("{}", e));
+		if idx >= count {
+			panic!("blinding key given for {} {}, but there are only {}", kind, idx, count);
+		}
+		(idx, sk)
+	}).collect()
+}
+
+fn exec_blind<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let blinding_keys = blinding_keys(matches, "input", pset.inputs().len());
+
+	let secp = secp256k1_zkp::Secp256k1::new();
+
+	// The blinding factors of the outputs are balanced against those of the inputs,
+	// so we need the secrets of every input.
+	let mut inp_secrets = HashMap::new();
+	for (i, input) in pset.inputs().iter().enumerate() {
+		let utxo = match (&input.witness_utxo, &input.non_witness_utxo) {
+			(&Some(ref utxo), _) => utxo,
+			(&None, &Some(ref tx)) => {
+				tx.output.get(input.previous_output_index as usize).unwrap_or_else(|| {
+					panic!("input {} spends an output missing in its non-witness utxo", i)
+				})
+			}
+			(&None, &None) => panic!("input {} has no witness or non-witness utxo", i),
+		};
+		let secrets = match (utxo.asset, utxo.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				TxOutSecrets::new(
+					asset,
+					confidential::AssetBlindingFactor::zero(),
+					value,
+					confidential::ValueBlindingFactor::zero(),
+				)
+			}
+			_ => {
+				let sk = blinding_keys.get(&i).unwrap_or_else(|| {
+					panic!("input {} is confidential, but no blinding key was given", i)
+				});
+				utxo.unblind(&secp, *sk).unwrap_or_else(|e| {
+					panic!("failed to unblind input {} with the given blinding key: {}", i, e)
+				})
+			}
+		};
+		inp_secrets.insert(i, secrets);
+	}
+
+	for (i, output) in pset.outputs().iter().enumerate() {
+		if output.blinding_key.is_none() {
+			continue;
+		}
+		if output.value_rangeproof.is_some() || output.asset_surjection_proof.is_some() {
+			panic!("output {} is already blinded", i);
+		}
+		if output.amount.is_none() || output.asset.is_none() {
+			panic!("output {} has no explicit amount and asset to blind", i);
+		}
+	}
+
+	pset.blind_last(&mut secp256k1_zkp::rand::thread_rng(), &secp, &inp_secrets)
+		.expect("failed to blind PSET");
+
+	write_pset(matches, source, &pset);
 }
 
 fn cmd_create<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("create", "create a PSET from an unsigned raw transaction").args(&[
-		cmd::arg("raw-tx", "the raw transaction in hex").required(true),
+	cmd::subcommand("create", "create a PSET from an unsigned raw transaction or JSON").args(&[
+		cmd::arg("raw-tx", "the raw transaction in hex").required_unless("json"),
+		cmd::opt("json", "create the PSET from its JSON info, as output by `pset decode`")
+			.takes_value(true)
+			.conflicts_with("raw-tx")
+			.required(false),
 		cmd::opt("output", "where to save the merged PSET output")
 			.short("o")
 			.takes_value(true)
@@ -78,11 +425,16 @@ fn cmd_create<'a>() -> clap::App<'a, 'a> {
 }
 
 fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
-	let hex_tx = matches.value_of("raw-tx").expect("no raw tx provided");
-	let raw_tx = hex::decode(hex_tx).expect("could not decode raw tx");
-	let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
-
-	let pset = Pset::from_tx(tx);
+	let pset = if let Some(json) = matches.value_of("json") {
+		let info: hal_elements::pset::PsetInfo = serde_json::from_str(json).expect("invalid JSON");
+		hal_elements::pset::create_pset(info)
+			.unwrap_or_else(|e| panic!("Invalid PSET info: {}", e))
+	} else {
+		let hex_tx = matches.value_of("raw-tx").expect("no raw tx provided");
+		let raw_tx = hex::decode(hex_tx).expect("could not decode raw tx");
+		let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
+		Pset::from_tx(tx)
+	};
 
 	let serialized = serialize(&pset);
 	if let Some(path) = matches.value_of("output") {
@@ -98,22 +450,77 @@ fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
 fn cmd_decode<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("decode", "decode a PSET to JSON").args(&cmd::opts_networks()).args(&[
 		cmd::opt_yaml(),
-		cmd::arg("pset", "the PSET file or raw PSET in base64/hex").required(true),
+		cmd::opt_cbor(),
+		cmd::opt_asset_labels(),
+		cmd::arg("pset", "the PSET file or raw PSET in base64/hex").required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("input", "only decode the input with this index")
+			.takes_value(true)
+			.multiple(true)
+			.number_of_values(1)
+			.required(false),
+		cmd::opt("output", "only decode the output with this index")
+			.takes_value(true)
+			.multiple(true)
+			.number_of_values(1)
+			.required(false),
+		cmd::opt("include-unsigned-tx", "also decode the unsigned transaction of the PSET")
+			.required(false),
 	])
 }
 
+/// The inputs and outputs selected with `--input` and `--output` in `pset decode`.
+#[derive(Serialize)]
+struct PsetSelectionInfo {
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	inputs: BTreeMap<usize, hal_elements::pset::PsetInputInfo>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	outputs: BTreeMap<usize, hal_elements::pset::PsetOutputInfo>,
+}
+
 fn exec_decode<'a>(matches: &clap::ArgMatches<'a>) {
-	let (raw_pset, _) = file_or_raw(matches.value_of("pset").unwrap());
+	let (raw_pset, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
 
 	let pset: pset::PartiallySignedTransaction = deserialize(&raw_pset).expect("invalid PSET");
+	let network = cmd::network(matches);
 
-	let info = hal_elements::GetInfo::get_info(&pset, cmd::network(matches));
-	cmd::print_output(matches, &info)
+	if matches.is_present("input") || matches.is_present("output") {
+		let indices = |name: &str, len: usize| -> Vec<usize> {
+			matches.values_of(name).map(|v| v.map(|i| {
+				let i = i.parse::<usize>().expect(&format!("invalid {} index", name));
+				if i >= len {
+					panic!("{} index {} out of range", name, i);
+				}
+				i
+			}).collect()).unwrap_or_default()
+		};
+		let info = PsetSelectionInfo {
+			inputs: indices("input", pset.inputs().len()).into_iter().map(|i| {
+				(i, hal_elements::GetInfo::get_info(&pset.inputs()[i], network))
+			}).collect(),
+			outputs: indices("output", pset.outputs().len()).into_iter().map(|i| {
+				(i, hal_elements::GetInfo::get_info(&pset.outputs()[i], network))
+			}).collect(),
+		};
+		cmd::print_output_network(matches, &info, network)
+	} else {
+		let mut info: hal_elements::pset::PsetInfo = hal_elements::GetInfo::get_info(&pset, network);
+		if matches.is_present("include-unsigned-tx") {
+			info.global.unsigned_tx = Some(
+				hal_elements::pset::unsigned_tx_info(&pset, network)
+					.expect("can't extract the transaction from the PSET"),
+			);
+		}
+		cmd::print_output_network(matches, &info, network)
+	}
 }
 
 fn cmd_edit<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("edit", "edit a PSET").args(&[
-		cmd::arg("pset", "PSET to edit, either base64/hex or a file path").required(true),
+		cmd::arg("pset", "PSET to edit, either base64/hex, a file path or `-` to read from stdin")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
 		cmd::opt("input-idx", "the input index to edit")
 			.display_order(1)
 			.takes_value(true)
@@ -131,6 +538,12 @@ fn cmd_edit<'a>() -> clap::App<'a, 'a> {
 		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
 			.short("r")
 			.required(false),
+		cmd::opt("print-source", "print on stderr whether the PSET was read as hex, base64 or a file")
+			.display_order(4)
+			.required(false),
+		cmd::opt("strip-unknown", "remove the unknown and proprietary fields")
+			.display_order(99)
+			.required(false),
 		//
 		// values used in both inputs and outputs
 		cmd::opt("redeem-script", "the redeem script")
@@ -214,16 +627,43 @@ fn parse_partial_sig_pair(pair_str: &str) -> (PublicKey, Vec<u8>) {
 	(pubkey, sig)
 }
 
+/// An error in an HD keypath triplet `<pubkey>:<master-fp>:<path>`.
+#[derive(Debug)]
+enum KeypathError {
+	/// The public key is invalid.
+	PublicKey(String),
+	/// The fingerprint is missing or not 4 bytes of hex.
+	Fingerprint(String),
+	/// The derivation path is missing or invalid.
+	Path(String),
+}
+
+impl fmt::Display for KeypathError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			KeypathError::PublicKey(ref e) => write!(f, "invalid public key: {}", e),
+			KeypathError::Fingerprint(ref e) => {
+				write!(f, "invalid master fingerprint, expected 4 bytes in hex: {}", e)
+			}
+			KeypathError::Path(ref e) => {
+				write!(f, "invalid derivation path, expected e.g. m/84'/0'/0'/0/1: {}", e)
+			}
+		}
+	}
+}
+
 fn parse_hd_keypath_triplet(
 	triplet_str: &str,
-) -> (PublicKey, (bip32::Fingerprint, bip32::DerivationPath)) {
+) -> Result<(PublicKey, (bip32::Fingerprint, bip32::DerivationPath)), KeypathError> {
 	let mut triplet = triplet_str.splitn(3, ":");
-	let pubkey = triplet.next().unwrap().parse().expect("invalid HD keypath pubkey");
+	let pubkey = triplet.next().unwrap().parse::<PublicKey>()
+		.map_err(|e| KeypathError::PublicKey(e.to_string()))?;
 	let fp = {
-		let hex = triplet.next().expect("invalid HD keypath triplet: missing fingerprint");
-		let raw = hex::decode(&hex).expect("invalid HD keypath fingerprint hex");
+		let hex = triplet.next()
+			.ok_or_else(|| KeypathError::Fingerprint("missing".to_owned()))?;
+		let raw = hex::decode(&hex).map_err(|e| KeypathError::Fingerprint(e.to_string()))?;
 		if raw.len() != 4 {
-			panic!("invalid HD keypath fingerprint size: {} instead of 4", raw.len());
+			return Err(KeypathError::Fingerprint(format!("got {} bytes", raw.len())));
 		}
 		let mut res = [0; 4];
 		res.copy_from_slice(&raw);
@@ -231,10 +671,16 @@ fn parse_hd_keypath_triplet(
 	};
 	let path = triplet
 		.next()
-		.expect("invalid HD keypath triplet: missing HD path")
+		.ok_or_else(|| KeypathError::Path("missing".to_owned()))?
 		.parse()
-		.expect("invalid derivation path format");
-	(pubkey, (fp, path))
+		.map_err(|e: bip32::Error| KeypathError::Path(e.to_string()))?;
+	Ok((pubkey, (fp, path)))
+}
+
+/// Parse an HD keypath triplet, panicking with the reason it's invalid.
+fn hd_keypath_or_panic(triplet_str: &str) -> (PublicKey, (bip32::Fingerprint, bip32::DerivationPath)) {
+	parse_hd_keypath_triplet(triplet_str)
+		.unwrap_or_else(|e| panic!("invalid HD keypath \"{}\": {}", triplet_str, e))
 }
 
 fn edit_input<'a>(
@@ -268,8 +714,9 @@ fn edit_input<'a>(
 	}
 
 	if let Some(sht) = matches.value_of("sighash-type") {
-		input.sighash_type = Some(
-			PsbtSighashType::from_u32(hal_elements::pset::sighashtype_from_string(&sht).as_u32()));
+		let sht = hal_elements::pset::sighashtype_from_string(&sht)
+			.unwrap_or_else(|e| panic!("{}", e));
+		input.sighash_type = Some(PsbtSighashType::from_u32(sht.as_u32()));
 	}
 
 	if let Some(hex) = matches.value_of("redeem-script") {
@@ -283,10 +730,10 @@ fn edit_input<'a>(
 	}
 
 	if let Some(csv) = matches.value_of("hd-keypaths") {
-		input.bip32_derivation = csv.split(",").map(parse_hd_keypath_triplet).collect();
+		input.bip32_derivation = csv.split(",").map(hd_keypath_or_panic).collect();
 	}
 	if let Some(triplets) = matches.values_of("hd-keypaths-add") {
-		for (pk, pair) in triplets.map(parse_hd_keypath_triplet) {
+		for (pk, pair) in triplets.map(hd_keypath_or_panic) {
 			if input.bip32_derivation.insert(pk, pair).is_some() {
 				panic!("public key {} is already in HD keypaths", &pk);
 			}
@@ -303,6 +750,11 @@ fn edit_input<'a>(
 		let vraw = vhex.map(|h| hex::decode(&h).expect("invalid final-script-witness hex"));
 		input.final_script_witness = Some(vraw.collect());
 	}
+
+	if matches.is_present("strip-unknown") {
+		input.unknown.clear();
+		input.proprietary.clear();
+	}
 }
 
 fn edit_output<'a>(
@@ -323,19 +775,26 @@ fn edit_output<'a>(
 	}
 
 	if let Some(csv) = matches.value_of("hd-keypaths") {
-		output.bip32_derivation = csv.split(",").map(parse_hd_keypath_triplet).collect();
+		output.bip32_derivation = csv.split(",").map(hd_keypath_or_panic).collect();
 	}
 	if let Some(triplets) = matches.values_of("hd-keypaths-add") {
-		for (pk, pair) in triplets.map(parse_hd_keypath_triplet) {
+		for (pk, pair) in triplets.map(hd_keypath_or_panic) {
 			if output.bip32_derivation.insert(pk, pair).is_some() {
 				panic!("public key {} is already in HD keypaths", &pk);
 			}
 		}
 	}
+
+	if matches.is_present("strip-unknown") {
+		output.unknown.clear();
+		output.proprietary.clear();
+	}
 }
 
 fn exec_edit<'a>(matches: &clap::ArgMatches<'a>) {
-	let (raw, source) = file_or_raw(&matches.value_of("pset").unwrap());
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	print_source(matches, &source);
 	let mut pset: pset::PartiallySignedTransaction =
 		deserialize(&raw).expect("invalid PSET format");
 
@@ -350,42 +809,79 @@ fn exec_edit<'a>(matches: &clap::ArgMatches<'a>) {
 		}
 	}
 
-	let edited_raw = serialize(&pset);
-	if let Some(path) = matches.value_of("output") {
-		let mut file = File::create(&path).expect("failed to open output file");
-		file.write_all(&edited_raw).expect("error writing output file");
-	} else if matches.is_present("raw-stdout") {
-		::std::io::stdout().write_all(&edited_raw).unwrap();
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_extract<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("extract", "extract the transaction from a PSET without finalizing it").args(&[
+		cmd::arg("pset", "PSET to extract, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+	])
+}
+
+fn exec_extract<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let pset: pset::PartiallySignedTransaction = deserialize(&raw).expect("invalid PSET format");
+
+	let extracted_raw = serialize(&pset.extract_tx().expect("Unable to extract tx"));
+	if matches.is_present("raw-stdout") {
+		::std::io::stdout().write_all(&extracted_raw).unwrap();
 	} else {
-		match source {
-			PsetSource::Hex => print!("{}", hex::encode(&edited_raw)),
-			PsetSource::Base64 => print!("{}", base64::encode(&edited_raw)),
-			PsetSource::File => {
-				let path = matches.value_of("pset").unwrap();
-				let mut file = File::create(&path).expect("failed to PSET file for writing");
-				file.write_all(&edited_raw).expect("error writing PSET file");
-			}
-		}
+		print!("{}", ::hex::encode(&extracted_raw));
 	}
 }
 
 fn cmd_finalize<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("finalize", "finalize a PSET and print the fully signed tx in hex").args(&[
-		cmd::arg("pset", "PSET to finalize, either base64/hex or a file path").required(true),
+		cmd::arg("pset", "PSET to finalize, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
 		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
 			.short("r")
 			.required(false),
-	])
+		cmd::opt("dry-run", "only report for each input whether it can be finalized")
+			.conflicts_with("raw-stdout")
+			.required(false),
+	]).args(&cmd::opts_networks_no_short())
+}
+
+/// Whether an input can be finalized, as reported by `pset finalize --dry-run`.
+#[derive(Serialize)]
+struct FinalizeInputInfo {
+	finalized: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
 }
 
 fn exec_finalize<'a>(matches: &clap::ArgMatches<'a>) {
-	let (raw, _) = file_or_raw(&matches.value_of("pset").unwrap());
+	let (raw, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
 	let mut pset: pset::PartiallySignedTransaction = deserialize(&raw).expect("invalid PSET format");
 
 
 	// Create a secp context, should there be one with static lifetime?
 	let secp = secp256k1_zkp::Secp256k1::verification_only();
-	::miniscriptlib::psbt::finalize(&mut pset, &secp, elements::BlockHash::all_zeros()).expect("failed to finalize");
+	let genesis_hash = if pset.inputs().iter().any(is_taproot_input) {
+		cmd::genesis_hash(matches)
+	} else {
+		BlockHash::all_zeros()
+	};
+	if matches.is_present("dry-run") {
+		// Finalize each input on a copy, so that the errors are reported per input.
+		let info = (0..pset.inputs().len()).map(|i| {
+			match pset.clone().finalize_inp_mut(&secp, i, genesis_hash) {
+				Ok(()) => FinalizeInputInfo { finalized: true, error: None },
+				Err(e) => FinalizeInputInfo { finalized: false, error: Some(e.to_string()) },
+			}
+		}).collect::<Vec<_>>();
+		return cmd::print_output(matches, &info);
+	}
+	::miniscriptlib::psbt::finalize(&mut pset, &secp, genesis_hash).expect("failed to finalize");
 
 	let finalized_raw = serialize(&pset.extract_tx().expect("Unable to extract tx"));
 	if matches.is_present("raw-stdout") {
@@ -395,11 +891,121 @@ fn exec_finalize<'a>(matches: &clap::ArgMatches<'a>) {
 	}
 }
 
+fn cmd_is_final<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("is-final", "exit with a non-zero status if not all PSET inputs are finalized")
+		.arg(
+			cmd::arg("pset", "PSET to check, either base64/hex or a file path")
+				.required_unless("raw-stdin"),
+		)
+		.arg(opt_raw_stdin())
+		.arg(cmd::opt("detail", "list the inputs that are not finalized").required(false))
+}
+
+fn exec_is_final<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let incomplete: Vec<usize> = pset
+		.inputs()
+		.iter()
+		.enumerate()
+		.filter(|(_, input)| input.final_script_sig.is_none() && input.final_script_witness.is_none())
+		.map(|(i, _)| i)
+		.collect();
+
+	if matches.is_present("detail") {
+		for i in &incomplete {
+			println!("input {} is not finalized", i);
+		}
+	}
+	if !incomplete.is_empty() {
+		::std::process::exit(1);
+	}
+}
+
+fn cmd_join<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("join", "join the inputs and outputs of multiple PSETs into one").args(&[
+		cmd::arg("psets", "PSETs to join; can be file paths or base64/hex")
+			.multiple(true)
+			.required_unless("raw-stdin"),
+		cmd::opt("raw-stdin", "also join the raw bytes of a PSET read from stdin, as the first one")
+			.required(false),
+		cmd::opt("output", "where to save the joined PSET output")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+	])
+}
+
+fn exec_join<'a>(matches: &clap::ArgMatches<'a>) {
+	let psets = matches.values_of("psets")
+		.map(|v| v.map(|s| s.to_owned()).collect::<Vec<_>>())
+		.unwrap_or_default();
+	let (raws, source) = psets_input(matches, &psets)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut parts = raws.iter().map(|raw| {
+		let pset: Pset = deserialize(&raw).expect("invalid PSET format");
+		pset
+	});
+
+	let mut joined = parts.next().unwrap();
+	for (idx, mut part) in parts.enumerate().map(|(i, p)| (i + 1, p)) {
+		if part.global.tx_data.version != joined.global.tx_data.version {
+			panic!("PSET #{} has a conflicting tx version", idx);
+		}
+		if part.global.tx_data.fallback_locktime != joined.global.tx_data.fallback_locktime {
+			panic!("PSET #{} has a conflicting fallback locktime", idx);
+		}
+
+		// Global fields are kept from all PSETs, as long as they don't conflict.
+		let global = &mut part.global;
+		dedup_map(&joined.global.xpub, &mut global.xpub, "xpub", idx);
+		dedup_map(&joined.global.proprietary, &mut global.proprietary, "global proprietary", idx);
+		dedup_map(&joined.global.unknown, &mut global.unknown, "global unknown", idx);
+		joined.global.xpub.append(&mut global.xpub);
+		joined.global.proprietary.append(&mut global.proprietary);
+		joined.global.unknown.append(&mut global.unknown);
+		for scalar in global.scalars.drain(..) {
+			if !joined.global.scalars.contains(&scalar) {
+				joined.global.scalars.push(scalar);
+			}
+		}
+
+		for input in part.inputs() {
+			let duplicate = joined.inputs().iter().any(|i| {
+				i.previous_txid == input.previous_txid
+					&& i.previous_output_index == input.previous_output_index
+			});
+			if duplicate {
+				panic!(
+					"PSET #{} spends {}:{} again",
+					idx, input.previous_txid, input.previous_output_index,
+				);
+			}
+			joined.add_input(input.clone());
+		}
+		for output in part.outputs() {
+			joined.add_output(output.clone());
+		}
+	}
+
+	write_pset(matches, source, &joined);
+}
+
 fn cmd_merge<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("merge", "merge multiple PSET files into one").args(&[
 		cmd::arg("psets", "PSETs to merge; can be file paths or base64/hex")
 			.multiple(true)
-			.required(true),
+			.required_unless_one(&["from-file", "raw-stdin"]),
+		cmd::opt("raw-stdin", "also merge the raw bytes of a PSET read from stdin, as the first one")
+			.required(false),
+		cmd::opt("from-file", "also merge the PSETs in this file, in base64/hex one per line")
+			.takes_value(true)
+			.required(false),
 		cmd::opt("output", "where to save the merged PSET output")
 			.short("o")
 			.takes_value(true)
@@ -407,42 +1013,290 @@ fn cmd_merge<'a>() -> clap::App<'a, 'a> {
 		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
 			.short("r")
 			.required(false),
+		cmd::opt("lenient", "ignore duplicate values and only fail on conflicting values")
+			.required(false),
 	])
 }
 
+/// Removes the entries of `other` that are also in `map`, failing when values differ.
+fn dedup_map<K: Ord, V: PartialEq>(
+	map: &BTreeMap<K, V>,
+	other: &mut BTreeMap<K, V>,
+	name: &str,
+	idx: usize,
+) {
+	for (k, v) in map {
+		if other.get(k).map(|o| o != v).unwrap_or(false) {
+			panic!("conflicting {} in #{}", name, idx);
+		}
+		other.remove(k);
+	}
+}
+
+/// Clears `other` if `field` has a value, failing when values differ.
+fn dedup_option<T: PartialEq>(field: &Option<T>, other: &mut Option<T>, name: &str, idx: usize) {
+	if let (Some(existing), Some(v)) = (field.as_ref(), other.as_ref()) {
+		if existing != v {
+			panic!("conflicting {} in #{}", name, idx);
+		}
+	}
+	if field.is_some() {
+		*other = None;
+	}
+}
+
+/// Merges two PSETs of the same transaction, only failing on conflicting values.
+///
+/// The values that are already in `merged` are removed from a copy of `part`, so that the
+/// native merge only has to add the new values of every field.
+fn merge_lenient(merged: &mut Pset, part: &Pset) {
+	if merged.inputs().len() != part.inputs().len()
+		|| merged.outputs().len() != part.outputs().len()
+	{
+		panic!("PSETs have a different number of inputs or outputs");
+	}
+	let mut part = part.clone();
+
+	let global = &mut part.global;
+	dedup_map(&merged.global.xpub, &mut global.xpub, "xpub", 0);
+	dedup_map(&merged.global.proprietary, &mut global.proprietary, "global proprietary", 0);
+	dedup_map(&merged.global.unknown, &mut global.unknown, "global unknown", 0);
+
+	for (idx, (input, other)) in merged.inputs().iter().zip(part.inputs_mut()).enumerate() {
+		if input.previous_txid != other.previous_txid
+			|| input.previous_output_index != other.previous_output_index
+		{
+			panic!("input #{} spends different outputs", idx);
+		}
+		dedup_map(&input.partial_sigs, &mut other.partial_sigs, "partial sig", idx);
+		dedup_map(&input.bip32_derivation, &mut other.bip32_derivation, "HD keypath", idx);
+		dedup_map(
+			&input.ripemd160_preimages,
+			&mut other.ripemd160_preimages,
+			"ripemd160 preimage",
+			idx,
+		);
+		dedup_map(&input.sha256_preimages, &mut other.sha256_preimages, "sha256 preimage", idx);
+		dedup_map(&input.hash160_preimages, &mut other.hash160_preimages, "hash160 preimage", idx);
+		dedup_map(&input.hash256_preimages, &mut other.hash256_preimages, "hash256 preimage", idx);
+		dedup_map(&input.tap_script_sigs, &mut other.tap_script_sigs, "tap script sig", idx);
+		dedup_map(&input.tap_scripts, &mut other.tap_scripts, "tap script", idx);
+		dedup_map(&input.proprietary, &mut other.proprietary, "proprietary", idx);
+		dedup_map(&input.unknown, &mut other.unknown, "unknown", idx);
+		dedup_option(&input.non_witness_utxo, &mut other.non_witness_utxo, "non-witness utxo", idx);
+		dedup_option(&input.witness_utxo, &mut other.witness_utxo, "witness utxo", idx);
+		dedup_option(&input.sighash_type, &mut other.sighash_type, "sighash type", idx);
+		dedup_option(&input.redeem_script, &mut other.redeem_script, "redeem script", idx);
+		dedup_option(&input.witness_script, &mut other.witness_script, "witness script", idx);
+		dedup_option(&input.final_script_sig, &mut other.final_script_sig, "final script sig", idx);
+		dedup_option(
+			&input.final_script_witness,
+			&mut other.final_script_witness,
+			"final script witness",
+			idx,
+		);
+		dedup_option(&input.sequence, &mut other.sequence, "sequence", idx);
+		dedup_option(
+			&input.required_time_locktime,
+			&mut other.required_time_locktime,
+			"required time locktime",
+			idx,
+		);
+		dedup_option(
+			&input.required_height_locktime,
+			&mut other.required_height_locktime,
+			"required height locktime",
+			idx,
+		);
+		dedup_option(&input.tap_key_sig, &mut other.tap_key_sig, "tap key sig", idx);
+		dedup_option(&input.tap_internal_key, &mut other.tap_internal_key, "tap internal key", idx);
+		dedup_option(&input.tap_merkle_root, &mut other.tap_merkle_root, "tap merkle root", idx);
+		dedup_option(
+			&input.issuance_value_amount,
+			&mut other.issuance_value_amount,
+			"issuance value",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_value_comm,
+			&mut other.issuance_value_comm,
+			"issuance value commitment",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_value_rangeproof,
+			&mut other.issuance_value_rangeproof,
+			"issuance value rangeproof",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_keys_rangeproof,
+			&mut other.issuance_keys_rangeproof,
+			"issuance keys rangeproof",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_inflation_keys,
+			&mut other.issuance_inflation_keys,
+			"issuance inflation keys",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_inflation_keys_comm,
+			&mut other.issuance_inflation_keys_comm,
+			"issuance inflation keys commitment",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_blinding_nonce,
+			&mut other.issuance_blinding_nonce,
+			"issuance blinding nonce",
+			idx,
+		);
+		dedup_option(
+			&input.issuance_asset_entropy,
+			&mut other.issuance_asset_entropy,
+			"issuance asset entropy",
+			idx,
+		);
+		dedup_option(&input.pegin_tx, &mut other.pegin_tx, "pegin tx", idx);
+		dedup_option(
+			&input.pegin_txout_proof,
+			&mut other.pegin_txout_proof,
+			"pegin txout proof",
+			idx,
+		);
+		dedup_option(
+			&input.pegin_genesis_hash,
+			&mut other.pegin_genesis_hash,
+			"pegin genesis hash",
+			idx,
+		);
+		dedup_option(
+			&input.pegin_claim_script,
+			&mut other.pegin_claim_script,
+			"pegin claim script",
+			idx,
+		);
+		dedup_option(&input.pegin_value, &mut other.pegin_value, "pegin value", idx);
+		dedup_option(&input.pegin_witness, &mut other.pegin_witness, "pegin witness", idx);
+	}
+
+	for (idx, (output, other)) in merged.outputs().iter().zip(part.outputs_mut()).enumerate() {
+		if output.script_pubkey != other.script_pubkey {
+			panic!("output #{} has a different scriptPubKey", idx);
+		}
+		dedup_map(&output.bip32_derivation, &mut other.bip32_derivation, "HD keypath", idx);
+		dedup_map(&output.proprietary, &mut other.proprietary, "proprietary", idx);
+		dedup_map(&output.unknown, &mut other.unknown, "unknown", idx);
+		dedup_option(&output.redeem_script, &mut other.redeem_script, "redeem script", idx);
+		dedup_option(&output.witness_script, &mut other.witness_script, "witness script", idx);
+		dedup_option(&output.amount, &mut other.amount, "amount", idx);
+		dedup_option(&output.amount_comm, &mut other.amount_comm, "amount commitment", idx);
+		dedup_option(&output.asset, &mut other.asset, "asset", idx);
+		dedup_option(&output.asset_comm, &mut other.asset_comm, "asset commitment", idx);
+		dedup_option(
+			&output.value_rangeproof,
+			&mut other.value_rangeproof,
+			"value rangeproof",
+			idx,
+		);
+		dedup_option(
+			&output.asset_surjection_proof,
+			&mut other.asset_surjection_proof,
+			"asset surjection proof",
+			idx,
+		);
+		dedup_option(
+			&output.blind_value_proof,
+			&mut other.blind_value_proof,
+			"blind value proof",
+			idx,
+		);
+		dedup_option(
+			&output.blind_asset_proof,
+			&mut other.blind_asset_proof,
+			"blind asset proof",
+			idx,
+		);
+		dedup_option(&output.blinding_key, &mut other.blinding_key, "blinding key", idx);
+		dedup_option(&output.ecdh_pubkey, &mut other.ecdh_pubkey, "ECDH pubkey", idx);
+		dedup_option(&output.blinder_index, &mut other.blinder_index, "blinder index", idx);
+		dedup_option(
+			&output.tap_internal_key,
+			&mut other.tap_internal_key,
+			"tap internal key",
+			idx,
+		);
+	}
+
+	merged.merge(part).unwrap_or_else(|e| panic!("error merging PSETs: {}", e));
+}
+
 fn exec_merge<'a>(matches: &clap::ArgMatches<'a>) {
-	let mut parts = matches.values_of("psets").unwrap().map(|f| {
-		let (raw, _) = file_or_raw(&f);
+	let mut psets = matches.values_of("psets")
+		.map(|v| v.map(|s| s.to_owned()).collect::<Vec<_>>())
+		.unwrap_or_default();
+	if let Some(path) = matches.value_of("from-file") {
+		let mut content = String::new();
+		File::open(&path).and_then(|mut f| f.read_to_string(&mut content))
+			.expect("failed to read PSETs file");
+		psets.extend(content.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).map(|l| l.to_owned()));
+	}
+	let (raws, source) = psets_input(matches, &psets)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	if raws.is_empty() {
+		panic!("no PSETs to merge");
+	}
+
+	let mut parts = raws.iter().map(|raw| {
 		let pset: pset::PartiallySignedTransaction =
 			deserialize(&raw).expect("invalid PSET format");
 		pset
 	});
 
+	let lenient = matches.is_present("lenient");
 	let mut merged = parts.next().unwrap();
 	for (idx, part) in parts.enumerate() {
-		// merge function checks if the psets are merge-able.
-		merged.merge(part).expect(&format!("error merging PSET #{}", idx));
+		if lenient {
+			let mut attempt = merged.clone();
+			match attempt.merge(part.clone()) {
+				Ok(()) => merged = attempt,
+				Err(e) => {
+					debug!("error merging PSET #{}: {}, skipping duplicate values", idx, e);
+					merge_lenient(&mut merged, &part);
+				}
+			}
+		} else {
+			// merge function checks if the psets are merge-able.
+			merged.merge(part).expect(&format!("error merging PSET #{}", idx));
+		}
 	}
 
-	let merged_raw = serialize(&merged);
-	if let Some(path) = matches.value_of("output") {
-		let mut file = File::create(&path).expect("failed to open output file");
-		file.write_all(&merged_raw).expect("error writing output file");
-	} else if matches.is_present("raw-stdout") {
-		::std::io::stdout().write_all(&merged_raw).unwrap();
-	} else {
-		print!("{}", base64::encode(&merged_raw));
-	}
+	write_pset(matches, source, &merged);
 }
 
 fn cmd_rawsign<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand("rawsign", "sign a pset with private key and add sig to partial sigs").args(&[
-		cmd::arg("pset", "PSET to finalize, either base64/hex or a file path").required(true),
+		cmd::arg("pset", "PSET to finalize, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
 		cmd::arg("input-idx", "the input index to edit").required(true),
-		cmd::arg("priv-key", "the private key in WIF/hex").required(true),
+		cmd::arg("priv-key", "the private key in WIF/hex").required_unless("xprv"),
 		cmd::arg("compressed", "Whether the corresponding pk is compressed")
 			.required(false)
 			.default_value("true"),
+		cmd::opt("xprv", "sign with the key derived from this xprv using the input's derivation info")
+			.takes_value(true)
+			.required(false)
+			.conflicts_with("priv-key"),
+		cmd::opt("sighash", "sign with this sighash type instead of the one of the input")
+			.takes_value(true)
+			.possible_values(hal_elements::pset::sighashtype_values())
+			.required(false),
+		cmd::opt("set-sighash-type", "also set the sighash type of the input to --sighash")
+			.requires("sighash")
+			.required(false),
 		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
 			.short("r")
 			.required(false),
@@ -450,41 +1304,41 @@ fn cmd_rawsign<'a>() -> clap::App<'a, 'a> {
 			.short("o")
 			.takes_value(true)
 			.required(false),
-	])
+		cmd::opt("print-source", "print on stderr whether the PSET was read as hex, base64 or a file")
+			.required(false),
+	]).args(&cmd::opts_networks_no_short())
 }
 
-fn exec_rawsign<'a>(matches: &clap::ArgMatches<'a>) {
-	let (raw, source) = file_or_raw(&matches.value_of("pset").unwrap());
-	let mut pset: pset::PartiallySignedTransaction = deserialize(&raw).expect("invalid PSET format");
-
-	let priv_key = matches.value_of("priv-key").expect("no key provided");
-	let i = matches.value_of("input-idx").expect("Input index not provided")
-		.parse::<usize>().expect("input-idx must be a positive integer");
-	let compressed = matches.value_of("compressed").unwrap()
-		.parse::<bool>().expect("Compressed must be boolean");
-
-	if i >= pset.inputs().len() {
-		panic!("Pset input index out of range")
+/// Parses a private key in WIF or hex.
+pub fn parse_priv_key(priv_key: &str) -> secp256k1_zkp::SecretKey {
+	if let Ok(privkey) = PrivateKey::from_str(&priv_key) {
+		privkey.inner
+	} else if let Ok(sk) = secp256k1_zkp::SecretKey::from_str(&priv_key) {
+		sk
+	} else {
+		panic!("invalid WIF/hex private key: {}", priv_key);
 	}
+}
 
+/// Creates an ECDSA signature for the given input, returning the public key and the signature
+/// with the sighash type appended, ready to be added to the partial sigs.
+fn sign_input(
+	secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
+	pset: &Pset,
+	i: usize,
+	sk: &secp256k1_zkp::SecretKey,
+	compressed: bool,
+) -> (PublicKey, Vec<u8>) {
 	// Call with age and height 0.
 	// TODO: Create a method to rust-bitcoin pset that outputs sighash
 	// Workaround using miniscript interpreter
-	let sighash_ty = pset.inputs_mut()[i].sighash_type.unwrap_or(elements::pset::PsbtSighashType::from_u32(1));
+	let sighash_ty = pset.inputs()[i].sighash_type.unwrap_or(elements::pset::PsbtSighashType::from_u32(1));
 	let tx = pset.extract_tx().expect("Unable to extract tx");
 	let mut sighash_cache = elements::sighash::SighashCache::new(&tx);
 	let msg = pset.sighash_msg(i, &mut sighash_cache, None, BlockHash::all_zeros())
 		.expect("Unable to create sighash message");
 
-	let sk = if let Ok(privkey) = PrivateKey::from_str(&priv_key) {
-		privkey.inner
-	} else if let Ok(sk) = secp256k1_zkp::SecretKey::from_str(&priv_key) {
-		sk
-	} else {
-		panic!("invalid WIF/hex private key: {}", priv_key);
-	};
-	let secp = secp256k1_zkp::Secp256k1::signing_only();
-	let pk = secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk);
+	let pk = secp256k1_zkp::PublicKey::from_secret_key(secp, &sk);
 	let pk = bitcoin::PublicKey {
 		compressed: compressed,
 		inner: pk,
@@ -492,24 +1346,557 @@ fn exec_rawsign<'a>(matches: &clap::ArgMatches<'a>) {
 	let secp_sig = secp.sign_ecdsa(&msg.to_secp_msg(), &sk);
 	let mut btc_sig = secp_sig.serialize_der().as_ref().to_vec();
 	btc_sig.push(sighash_ty.to_u32() as u8); // Safe cast of sighash type. Will be improved in rust-elements later
+	(pk, btc_sig)
+}
+
+/// Creates a BIP341 key-spend signature for the given taproot input.
+fn sign_input_taproot(
+	secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
+	pset: &Pset,
+	i: usize,
+	sk: &secp256k1_zkp::SecretKey,
+	genesis_hash: BlockHash,
+) -> SchnorrSig {
+	let hash_ty = match pset.inputs()[i].sighash_type {
+		Some(sht) => sht.schnorr_hash_ty().expect("invalid taproot sighash type"),
+		None => SchnorrSighashType::Default,
+	};
+	// Taproot sighashes commit to all spent outputs.
+	let missing = pset.inputs().iter().enumerate()
+		.filter(|&(_, inp)| inp.witness_utxo.is_none())
+		.map(|(idx, _)| idx.to_string())
+		.collect::<Vec<_>>();
+	if !missing.is_empty() {
+		panic!(
+			"Signing taproot input {} requires the witness utxo of all inputs, missing for: {}",
+			i, missing.join(", "),
+		);
+	}
+	let prevouts = pset.inputs().iter()
+		.map(|inp| inp.witness_utxo.clone().unwrap())
+		.collect::<Vec<_>>();
+
+	let tx = pset.extract_tx().expect("Unable to extract tx");
+	let mut sighash_cache = elements::sighash::SighashCache::new(&tx);
+	let sighash = sighash_cache.taproot_key_spend_signature_hash(
+		i,
+		&elements::sighash::Prevouts::All(&prevouts),
+		hash_ty,
+		genesis_hash,
+	).expect("Unable to create taproot sighash");
+	let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).expect("sighash is 32 bytes");
+
+	let keypair = secp256k1_zkp::KeyPair::from_secret_key(secp, sk)
+		.tap_tweak(secp, pset.inputs()[i].tap_merkle_root)
+		.to_inner();
+	SchnorrSig {
+		sig: secp.sign_schnorr_no_aux_rand(&msg, &keypair),
+		hash_ty: hash_ty,
+	}
+}
+
+fn is_taproot_input(input: &pset::Input) -> bool {
+	input.witness_utxo.as_ref().map(|utxo| utxo.script_pubkey.is_v1_p2tr()).unwrap_or(false)
+}
+
+
+/// Derive the private key for the input from the master xprv using the input's BIP32
+/// derivation info, returning the key and whether its public key is compressed.
+fn derive_input_key(
+	secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
+	input: &pset::Input,
+	xprv: &bip32::ExtendedPrivKey,
+) -> (secp256k1_zkp::SecretKey, bool) {
+	if input.bip32_derivation.is_empty() {
+		panic!("Input has no BIP32 derivation info to derive the key from.");
+	}
+
+	let fingerprint = xprv.fingerprint(secp);
+	let (pk, &(_, ref path)) = input.bip32_derivation.iter()
+		.find(|&(_, &(fp, _))| fp == fingerprint)
+		.unwrap_or_else(|| panic!("No BIP32 derivation info for master fingerprint {}", fingerprint));
+	let sk = xprv.derive_priv(secp, path).expect("failed to derive key").private_key;
+
+	if secp256k1_zkp::PublicKey::from_secret_key(secp, &sk) != pk.inner {
+		panic!("Derived key does not match the public key {} of the derivation info", pk);
+	}
+	(sk, pk.compressed)
+}
+
+fn exec_rawsign<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	print_source(matches, &source);
+	let mut pset: pset::PartiallySignedTransaction = deserialize(&raw).expect("invalid PSET format");
+
+	let i = matches.value_of("input-idx").expect("Input index not provided")
+		.parse::<usize>().expect("input-idx must be a positive integer");
+	let compressed = matches.value_of("compressed").unwrap()
+		.parse::<bool>().expect("Compressed must be boolean");
+
+	if i >= pset.inputs().len() {
+		panic!("Pset input index out of range")
+	}
+
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let (sk, compressed) = if let Some(xprv) = matches.value_of("xprv") {
+		let xprv = bip32::ExtendedPrivKey::from_str(xprv).expect("invalid xprv");
+		derive_input_key(&secp, &pset.inputs()[i], &xprv)
+	} else {
+		let priv_key = matches.value_of("priv-key").expect("no key provided");
+		(parse_priv_key(&priv_key), compressed)
+	};
+
+	// The sighash is computed from the input's sighash type, so we temporarily
+	// override it unless it should be kept.
+	let original_sighash_type = pset.inputs()[i].sighash_type;
+	if let Some(sht) = matches.value_of("sighash") {
+		let sht = hal_elements::pset::sighashtype_from_string(&sht)
+			.unwrap_or_else(|e| panic!("{}", e));
+		pset.inputs_mut()[i].sighash_type = Some(PsbtSighashType::from_u32(sht.as_u32()));
+	}
 
 	// mutate the pset
-	pset.inputs_mut()[i].partial_sigs.insert(pk, btc_sig);
-	let raw = serialize(&pset);
-	if let Some(path) = matches.value_of("output") {
-		let mut file = File::create(&path).expect("failed to open output file");
-		file.write_all(&raw).expect("error writing output file");
-	} else if matches.is_present("raw-stdout") {
-		::std::io::stdout().write_all(&raw).unwrap();
+	if is_taproot_input(&pset.inputs()[i]) {
+		let sig = sign_input_taproot(&secp, &pset, i, &sk, cmd::genesis_hash(matches));
+		pset.inputs_mut()[i].tap_key_sig = Some(sig);
 	} else {
-		match source {
-			PsetSource::Hex => println!("{}", hex::encode(&raw)),
-			PsetSource::Base64 => println!("{}", base64::encode(&raw)),
-			PsetSource::File => {
-				let path = matches.value_of("pset").unwrap();
-				let mut file = File::create(&path).expect("failed to PSET file for writing");
-				file.write_all(&raw).expect("error writing PSET file");
+		let (pk, btc_sig) = sign_input(&secp, &pset, i, &sk, compressed);
+		pset.inputs_mut()[i].partial_sigs.insert(pk, btc_sig);
+	}
+	if !matches.is_present("set-sighash-type") {
+		pset.inputs_mut()[i].sighash_type = original_sighash_type;
+	}
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_bump_fee<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("bump-fee", "increase the fee of a PSET by reducing a change output").args(&[
+		cmd::arg("pset", "PSET to bump, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::opt("additional-fee", "the amount in satoshi to add to the fee")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("change-index", "the index of the output the fee is subtracted from")
+			.takes_value(true)
+			.required(true),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+		cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+	]).args(&cmd::opts_networks_no_short())
+}
+
+fn exec_bump_fee<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let additional_fee = matches.value_of("additional-fee").unwrap()
+		.parse::<u64>().expect("invalid additional fee");
+	let change_idx = matches.value_of("change-index").unwrap()
+		.parse::<usize>().expect("invalid change index");
+
+	let network = cmd::network(matches);
+	let policy_asset = match network.policy_asset() {
+		Some(asset) => asset,
+		None => panic!("The policy asset of network {} is not known, use --policy-asset.",
+			network.as_str()),
+	};
+
+	let (asset, change_value) = match pset.outputs().get(change_idx) {
+		Some(&pset::Output { amount: Some(value), asset: Some(asset), .. }) => (asset, value),
+		Some(_) => panic!("Change output {} must have an explicit asset and value.", change_idx),
+		None => panic!("Change output index {} out of range.", change_idx),
+	};
+	{
+		// Changing the value would invalidate any commitments or proofs of the output.
+		let change = &pset.outputs()[change_idx];
+		if change.amount_comm.is_some() || change.asset_comm.is_some()
+			|| change.value_rangeproof.is_some() || change.asset_surjection_proof.is_some()
+			|| change.blind_value_proof.is_some() || change.blind_asset_proof.is_some()
+		{
+			panic!("Change output {} is blinded or has blinding proofs.", change_idx);
+		}
+		if hal_elements::tx::is_fee_output(&change.to_txout(), Some(policy_asset)) {
+			panic!("Change output {} is the fee output.", change_idx);
+		}
+	}
+	if asset != policy_asset {
+		panic!("Change output {} has asset {}, but the fee must be paid in the policy asset {}.",
+			change_idx, asset, policy_asset);
+	}
+	if additional_fee > change_value {
+		panic!("Change output value {} is not enough to pay {} more fee.", change_value, additional_fee);
+	}
+
+	let fee_idx = pset.outputs().iter()
+		.position(|o| hal_elements::tx::is_fee_output(&o.to_txout(), Some(policy_asset)));
+	match fee_idx {
+		Some(i) => {
+			let fee_output = &mut pset.outputs_mut()[i];
+			let fee = fee_output.amount.expect("fee output has an explicit value");
+			fee_output.amount = Some(fee.checked_add(additional_fee).expect("fee overflows"));
+		}
+		None => pset.add_output(
+			pset::Output::new_explicit(elements::Script::new(), additional_fee, policy_asset, None),
+		),
+	}
+	pset.outputs_mut()[change_idx].amount = Some(change_value - additional_fee);
+
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_rawsign_all<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("rawsign-all", "sign all PSET inputs spendable by a private key").args(&[
+		cmd::arg("pset", "PSET to sign, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::arg("priv-key", "the private key in WIF/hex").required(true),
+		cmd::arg("compressed", "Whether the corresponding pk is compressed")
+			.required(false)
+			.default_value("true"),
+		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+			.short("r")
+			.required(false),
+		cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+			.short("o")
+			.takes_value(true)
+			.required(false),
+	]).args(&cmd::opts_networks_no_short())
+}
+
+/// Whether the input can be spent by the given key.
+///
+/// For taproot inputs, the key must be the internal key of the output key. For other inputs,
+/// the scriptPubKey must be a p2pk, p2pkh, p2wpkh or p2sh-p2wpkh output of the key, the key or
+/// its hash must be pushed in the redeem or witness script, like in multisig, or the key must
+/// have derivation info.
+fn input_spendable_by(
+	secp: &secp256k1_zkp::Secp256k1<secp256k1_zkp::All>,
+	input: &pset::Input,
+	sk: &secp256k1_zkp::SecretKey,
+	pk: &PublicKey,
+) -> bool {
+	let utxo = match (&input.witness_utxo, &input.non_witness_utxo) {
+		(&Some(ref utxo), _) => Some(utxo),
+		(&None, &Some(ref tx)) => tx.output.get(input.previous_output_index as usize),
+		(&None, &None) => None,
+	};
+	let spk = match utxo {
+		Some(utxo) => utxo.script_pubkey.clone(),
+		None => return false,
+	};
+
+	if spk.is_v1_p2tr() {
+		let keypair = secp256k1_zkp::KeyPair::from_secret_key(secp, sk)
+			.tap_tweak(secp, input.tap_merkle_root)
+			.to_inner();
+		return spk.as_bytes()[2..] == keypair.x_only_public_key().0.serialize()[..];
+	}
+
+	let pk_bytes = pk.to_bytes();
+	let pkh = PubkeyHash::hash(&pk_bytes);
+	let wpkh = Script::new_v0_wpkh(&WPubkeyHash::hash(&pk_bytes));
+	let p2pk = Builder::new().push_key(pk).push_opcode(opcodes::all::OP_CHECKSIG).into_script();
+	if spk == Script::new_p2pkh(&pkh)
+		|| spk == Script::new_p2sh(&ScriptHash::hash(wpkh.as_bytes()))
+		|| spk == wpkh
+		|| spk == p2pk
+	{
+		return true;
+	}
+
+	let has_key = |script: &Script| script.instructions().any(|ins| match ins {
+		Ok(Instruction::PushBytes(data)) => data == &pk_bytes[..] || data == &pkh[..],
+		_ => false,
+	});
+	input.redeem_script.as_ref().map(&has_key).unwrap_or(false)
+		|| input.witness_script.as_ref().map(&has_key).unwrap_or(false)
+		|| input.bip32_derivation.contains_key(pk)
+}
+
+fn exec_rawsign_all<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let priv_key = matches.value_of("priv-key").expect("no key provided");
+	let compressed = matches.value_of("compressed").unwrap()
+		.parse::<bool>().expect("Compressed must be boolean");
+
+	let sk = parse_priv_key(&priv_key);
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let pk = PublicKey {
+		compressed: compressed,
+		inner: secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk),
+	};
+
+	let mut signed = Vec::new();
+	for i in 0..pset.inputs().len() {
+		if !input_spendable_by(&secp, &pset.inputs()[i], &sk, &pk) {
+			continue;
+		}
+
+		if is_taproot_input(&pset.inputs()[i]) {
+			let sig = sign_input_taproot(&secp, &pset, i, &sk, cmd::genesis_hash(matches));
+			pset.inputs_mut()[i].tap_key_sig = Some(sig);
+		} else {
+			let (pk, btc_sig) = sign_input(&secp, &pset, i, &sk, compressed);
+			pset.inputs_mut()[i].partial_sigs.insert(pk, btc_sig);
+		}
+		signed.push(i);
+	}
+
+	if signed.is_empty() {
+		panic!("No input can be signed with the given key.");
+	}
+	eprintln!(
+		"signed inputs: {}",
+		signed.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", "),
+	);
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_sighash<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("sighash", "print the sighash of a PSET input without signing it").args(&[
+		cmd::arg("pset", "PSET to compute the sighash for, either base64/hex or a file path")
+			.required_unless("raw-stdin"),
+		opt_raw_stdin(),
+		cmd::arg("input-idx", "the input index to compute the sighash for").required(true),
+	]).args(&cmd::opts_networks())
+}
+
+fn exec_sighash<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let i = matches.value_of("input-idx").expect("Input index not provided")
+		.parse::<usize>().expect("input-idx must be a positive integer");
+	if i >= pset.inputs().len() {
+		panic!("Pset input index out of range")
+	}
+	// Only taproot sighashes commit to the genesis hash.
+	let genesis_hash = if is_taproot_input(&pset.inputs()[i]) {
+		cmd::genesis_hash(matches)
+	} else {
+		BlockHash::all_zeros()
+	};
+
+	// This is the same message that is signed by rawsign.
+	let tx = pset.extract_tx().expect("Unable to extract tx");
+	let mut sighash_cache = elements::sighash::SighashCache::new(&tx);
+	let msg = pset.sighash_msg(i, &mut sighash_cache, None, genesis_hash)
+		.expect("Unable to create sighash message");
+	println!("{}", hex::encode(&msg.to_secp_msg()[..]));
+}
+
+fn cmd_sign_with_descriptor<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("sign-with-descriptor", "sign a PSET input described by an output descriptor")
+		.args(&[
+			cmd::arg("pset", "PSET to sign, either base64/hex or a file path")
+				.required_unless("raw-stdin"),
+			opt_raw_stdin(),
+			cmd::arg("input-idx", "the input index to sign").required(true),
+			cmd::arg("priv-key", "the private key in WIF/hex").required(true),
+			cmd::opt("descriptor", "the output descriptor of the spent output")
+				.takes_value(true)
+				.required(true),
+			cmd::opt("derivation-index", "the index to derive a ranged descriptor at")
+				.takes_value(true)
+				.default_value("0"),
+			cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+				.short("r")
+				.required(false),
+			cmd::opt("output", "where to save the resulting PSET file -- in place if omitted")
+				.short("o")
+				.takes_value(true)
+				.required(false),
+		])
+		.args(&cmd::opts_networks_no_short())
+}
+
+fn exec_sign_with_descriptor<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, source) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let mut pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let i = matches.value_of("input-idx").expect("Input index not provided")
+		.parse::<usize>().expect("input-idx must be a positive integer");
+	if i >= pset.inputs().len() {
+		panic!("Pset input index out of range")
+	}
+
+	let derivation_index = matches.value_of("derivation-index").unwrap()
+		.parse::<u32>().expect("invalid derivation index");
+	let desc = Descriptor::<DescriptorPublicKey>::from_str(matches.value_of("descriptor").unwrap())
+		.expect("invalid descriptor")
+		.at_derivation_index(derivation_index)
+		.expect("can't derive descriptor at the given index");
+
+	// This fills in the redeem and witness scripts and checks that the descriptor
+	// matches the scriptPubKey of the spent output.
+	pset.update_input_with_descriptor(i, &desc)
+		.expect("failed to update input with descriptor");
+
+	let sk = parse_priv_key(matches.value_of("priv-key").expect("no key provided"));
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let pk = secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk);
+	if is_taproot_input(&pset.inputs()[i]) {
+		// Only key path spends are supported.
+		if pset.inputs()[i].tap_internal_key != Some(pk.x_only_public_key().0) {
+			panic!("The key is not the internal key, script path spends are not supported.");
+		}
+		let sig = sign_input_taproot(&secp, &pset, i, &sk, cmd::genesis_hash(matches));
+		pset.inputs_mut()[i].tap_key_sig = Some(sig);
+	} else {
+		// The descriptor keys are added as HD keypaths, so we can see if ours is compressed.
+		let compressed = pset.inputs()[i].bip32_derivation.keys()
+			.find(|k| k.inner == pk)
+			.map(|k| k.compressed)
+			.expect("The key is not in the descriptor.");
+		let (pk, btc_sig) = sign_input(&secp, &pset, i, &sk, compressed);
+		pset.inputs_mut()[i].partial_sigs.insert(pk, btc_sig);
+	}
+
+	write_pset(matches, source, &pset);
+}
+
+fn cmd_unblind<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("unblind", "unblind the confidential outputs of a PSET")
+		.args(&cmd::opts_networks())
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::arg("pset", "PSET to unblind, either base64/hex or a file path")
+				.required_unless("raw-stdin"),
+			opt_raw_stdin(),
+			cmd::opt("blinding-key", "the blinding secret of an output `<output-idx>:<secret>`")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.required(false),
+			cmd::opt("master-blinding-key", "the SLIP-77 master blinding key in hex")
+				.takes_value(true)
+				.required(false),
+		])
+}
+
+fn exec_unblind<'a>(matches: &clap::ArgMatches<'a>) {
+	let (raw, _) = pset_input(matches)
+		.unwrap_or_else(|e| panic!("Can't load PSET: {}", e));
+	let pset: Pset = deserialize(&raw).expect("invalid PSET format");
+
+	let blinding_keys = blinding_keys(matches, "output", pset.outputs().len());
+	let master_key = matches.value_of("master-blinding-key").map(|k| {
+		slip77::MasterBlindingKey(
+			secp256k1_zkp::SecretKey::from_str(&k).expect("invalid master blinding key"),
+		)
+	});
+
+	let network = cmd::network(matches);
+	let tx = pset.extract_tx().expect("Unable to extract tx");
+
+	// Outputs that can't be unblinded with the given keys are reported as null.
+	let info: Vec<Option<TxOutSecretsInfo>> = tx.output.iter().enumerate().map(|(i, txout)| {
+		let sk = match blinding_keys.get(&i) {
+			Some(sk) => *sk,
+			None => match master_key {
+				Some(ref mbk) => mbk.blinding_private_key(&txout.script_pubkey),
+				None => return None,
+			},
+		};
+		match hal_elements::confidential::unblind_txout(txout, &txout.witness, &sk) {
+			Ok(secrets) => Some(hal_elements::GetInfo::get_info(&secrets, network)),
+			Err(e) => {
+				debug!("Failed to unblind output {}: {}", i, e);
+				None
 			}
 		}
+	}).collect();
+	cmd::print_output_network(matches, &info, network)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use elements::pset::raw::{Key, ProprietaryKey};
+
+	fn unknown_key() -> Key {
+		Key {
+			type_value: 0xee,
+			key: vec![1, 2, 3],
+		}
 	}
-}
\ No newline at end of file
+
+	fn proprietary_key() -> ProprietaryKey {
+		ProprietaryKey {
+			prefix: b"test".to_vec(),
+			subtype: 0x01,
+			key: vec![4, 5, 6],
+		}
+	}
+
+	/// A PSET with an input and an output that both have unknown and proprietary fields.
+	fn pset_with_unknowns() -> Pset {
+		let mut input = pset::Input::from_prevout(OutPoint::new(Txid::all_zeros(), 0));
+		input.unknown.insert(unknown_key(), vec![0xaa]);
+		input.proprietary.insert(proprietary_key(), vec![0xbb]);
+
+		let asset = AssetId::from_slice(&[1; 32]).unwrap();
+		let mut output = pset::Output::new_explicit(Script::new(), 1000, asset, None);
+		output.unknown.insert(unknown_key(), vec![0xcc]);
+		output.proprietary.insert(proprietary_key(), vec![0xdd]);
+
+		let mut pset = Pset::new_v2();
+		pset.add_input(input);
+		pset.add_output(output);
+		deserialize(&serialize(&pset)).unwrap()
+	}
+
+	/// Edit the PSET like `pset edit` with the given arguments and decode the result.
+	fn edit(args: &[&str]) -> Pset {
+		let mut pset = pset_with_unknowns();
+		let mut argv = vec!["edit", "-"];
+		argv.extend_from_slice(args);
+		let matches = cmd_edit().get_matches_from(argv);
+		if let Some(idx) = matches.value_of("input-idx") {
+			edit_input(idx.parse().unwrap(), &matches, &mut pset);
+		}
+		if let Some(idx) = matches.value_of("output-idx") {
+			edit_output(idx.parse().unwrap(), &matches, &mut pset);
+		}
+		deserialize(&serialize(&pset)).unwrap()
+	}
+
+	#[test]
+	fn edit_keeps_unknown_fields() {
+		for pset in &[edit(&["--input-idx", "0"]), edit(&["--output-idx", "0"])] {
+			assert_eq!(pset.inputs()[0].unknown.get(&unknown_key()), Some(&vec![0xaa]));
+			assert_eq!(pset.inputs()[0].proprietary.get(&proprietary_key()), Some(&vec![0xbb]));
+			assert_eq!(pset.outputs()[0].unknown.get(&unknown_key()), Some(&vec![0xcc]));
+			assert_eq!(pset.outputs()[0].proprietary.get(&proprietary_key()), Some(&vec![0xdd]));
+		}
+	}
+
+	#[test]
+	fn edit_strips_unknown_fields() {
+		let pset = edit(&["--input-idx", "0", "--strip-unknown"]);
+		assert!(pset.inputs()[0].unknown.is_empty());
+		assert!(pset.inputs()[0].proprietary.is_empty());
+		assert!(!pset.outputs()[0].unknown.is_empty());
+		assert!(!pset.outputs()[0].proprietary.is_empty());
+
+		let pset = edit(&["--output-idx", "0", "--strip-unknown"]);
+		assert!(!pset.inputs()[0].unknown.is_empty());
+		assert!(!pset.inputs()[0].proprietary.is_empty());
+		assert!(pset.outputs()[0].unknown.is_empty());
+		assert!(pset.outputs()[0].proprietary.is_empty());
+	}
+}
@@ -1,451 +1,505 @@
+use std::fs::File;
 use std::io::Write;
 
-use clap;
 use bitcoin;
-use elements::{hashes::Hash, secp256k1_zkp::{RangeProof, SurjectionProof}};
-use elements::Script;
-use elements::secp256k1_zkp;
+use clap;
 use elements::encode::{deserialize, serialize};
-use elements::{
-	confidential, AssetIssuance, OutPoint, Transaction, TxIn, TxInWitness, TxOut, TxOutWitness,
-};
+use elements::secp256k1_zkp;
+use elements::hashes::Hash;
+use elements::script::Builder;
+use elements::{confidential, BlockHash, ContractHash, OutPoint, Transaction, TxOut};
+use elements::{PubkeyHash, Script, ScriptHash, WPubkeyHash};
+use serde::Serialize;
 
 use cmd;
-use hal_elements::tx::{InputScriptInfo, OutputScriptInfo};
-use hal_elements::confidential::{
-	ConfidentialAssetInfo, ConfidentialNonceInfo, ConfidentialType, ConfidentialValueInfo,
-};
+use hal_elements::tx::TransactionInfo;
 use hal_elements::Network;
-use hal_elements::tx::{
-	AssetIssuanceInfo, InputInfo, InputWitnessInfo, OutputInfo, OutputWitnessInfo, PeginDataInfo,
-	PegoutDataInfo, TransactionInfo,
-};
+use miniscriptlib::interpreter::Interpreter;
 
 pub fn subcommand<'a>() -> clap::App<'a, 'a> {
 	cmd::subcommand_group("tx", "manipulate transactions")
+		.subcommand(cmd_asset_id())
 		.subcommand(cmd_create())
 		.subcommand(cmd_decode())
+		.subcommand(cmd_fee())
+		.subcommand(cmd_sign())
+		.subcommand(cmd_size())
+		.subcommand(cmd_verify())
+		.subcommand(cmd_weight())
 }
 
 pub fn execute<'a>(matches: &clap::ArgMatches<'a>) {
 	match matches.subcommand() {
+		("asset-id", Some(ref m)) => exec_asset_id(&m),
 		("create", Some(ref m)) => exec_create(&m),
 		("decode", Some(ref m)) => exec_decode(&m),
+		("fee", Some(ref m)) => exec_fee(&m),
+		("sign", Some(ref m)) => exec_sign(&m),
+		("size", Some(ref m)) => exec_size(&m),
+		("verify", Some(ref m)) => exec_verify(&m),
+		("weight", Some(ref m)) => exec_weight(&m),
 		(_, _) => unreachable!("clap prints help"),
 	};
 }
 
+fn cmd_asset_id<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("asset-id", "compute the asset and reissuance token ids of a new issuance")
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::opt("txid", "the txid of the output spent by the issuance input")
+				.takes_value(true)
+				.required(true),
+			cmd::opt("vout", "the index of the output spent by the issuance input")
+				.takes_value(true)
+				.required(true),
+			cmd::opt("entropy", "the contract hash used as issuance entropy in hex")
+				.takes_value(true)
+				.required(true),
+			cmd::opt("confidential", "whether the issuance amount is confidential")
+				.required(false),
+		])
+}
+
+fn exec_asset_id<'a>(matches: &clap::ArgMatches<'a>) {
+	let txid = matches.value_of("txid").unwrap().parse().expect("invalid txid");
+	let vout = matches.value_of("vout").unwrap().parse().expect("invalid vout");
+	let entropy = hex::decode(matches.value_of("entropy").unwrap()).expect("invalid entropy hex");
+	let contract_hash = ContractHash::from_byte_array(
+		hal_elements::tx::fixed_bytes::<32>("entropy", &entropy).unwrap_or_else(|e| panic!("{}", e)),
+	);
+
+	let info = hal_elements::tx::IssuanceIdsInfo::new_issuance(
+		OutPoint::new(txid, vout),
+		contract_hash,
+		matches.is_present("confidential"),
+	);
+	cmd::print_output(matches, &info)
+}
+
 fn cmd_create<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("create", "create a raw transaction from JSON").args(&[
-		cmd::arg("tx-info", "the transaction info in JSON").required(true),
-		cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
-			.short("r")
-			.required(false),
-	])
+	cmd::subcommand("create", "create a raw transaction from JSON")
+		.args(&cmd::opts_networks_no_short())
+		.args(&[
+			cmd::arg("tx-info", "the transaction info in JSON")
+				.required_unless("json-file")
+				.conflicts_with("json-file"),
+			cmd::opt("json-file", "read the transaction info in JSON from a file")
+				.takes_value(true)
+				.required(false),
+			cmd::opt("fee-rate", "add a fee output paying this fee rate in sat/vB")
+				.takes_value(true)
+				.requires("change-index")
+				.required(false),
+			cmd::opt("change-index", "the index of the output the fee is subtracted from")
+				.takes_value(true)
+				.required(false),
+			cmd::opt("raw-stdout", "output the raw bytes of the result to stdout")
+				.short("r")
+				.required(false),
+			cmd::opt("output", "write the raw transaction to this file")
+				.short("o")
+				.takes_value(true)
+				.conflicts_with("raw-stdout")
+				.required(false),
+			cmd::opt("default-version", "the tx version to use if not given in the JSON")
+				.takes_value(true)
+				.required(false),
+			cmd::opt("default-locktime", "the locktime to use if not given in the JSON")
+				.takes_value(true)
+				.required(false),
+			cmd::opt("sort-bip69", "sort the inputs and outputs as described in BIP69")
+				.required(false),
+		])
+}
+
+/// Sort the inputs and outputs of the transaction as described in BIP69.
+///
+/// Inputs are sorted by txid as displayed and vout. Outputs are sorted by asset, value and
+/// scriptPubKey, comparing the serialized asset and value. Explicit values are serialized
+/// big-endian, so these sort numerically, followed by the confidential ones sorted by their
+/// commitment.
+fn sort_bip69(tx: &mut Transaction) {
+	tx.input.sort_by_key(|i| (i.previous_output.txid.to_string(), i.previous_output.vout));
+	tx.output.sort_by(|a, b| {
+		(serialize(&a.asset), serialize(&a.value), a.script_pubkey.as_bytes())
+			.cmp(&(serialize(&b.asset), serialize(&b.value), b.script_pubkey.as_bytes()))
+	});
 }
 
-/// Check both ways to specify the outpoint and panic if conflicting.
-fn outpoint_from_input_info(input: &InputInfo) -> OutPoint {
-	let op1: Option<OutPoint> =
-		input.prevout.as_ref().map(|ref op| op.parse().expect("invalid prevout format"));
-	let op2 = match input.txid {
-		Some(txid) => match input.vout {
-			Some(vout) => Some(OutPoint {
-				txid: txid,
-				vout: vout,
-			}),
-			None => panic!("\"txid\" field given in input without \"vout\" field"),
-		},
-		None => None,
+/// Add a fee output paying the given fee rate, subtracting the fee from the change output.
+///
+/// The fee is paid in the policy asset of the network, so the change output must be in it too.
+/// The weight is estimated from the transaction as it is, so signatures that are
+/// yet to be added are not accounted for.
+fn add_fee_output(
+	tx: &mut Transaction,
+	fee_rate: f64,
+	change_idx: usize,
+	network: Network,
+) {
+	let policy_asset = match network.policy_asset() {
+		Some(asset) => asset,
+		None => panic!("The policy asset of network {} is not known, use --policy-asset.",
+			network.as_str()),
 	};
+	if tx.output.iter().any(|o| hal_elements::tx::is_fee_output(o, Some(policy_asset))) {
+		warn!("Transaction already has a fee output, not adding one.");
+		return;
+	}
 
-	match (op1, op2) {
-		(Some(op1), Some(op2)) => {
-			if op1 != op2 {
-				panic!("Conflicting prevout information in input.");
-			}
-			op1
-		}
-		(Some(op), None) => op,
-		(None, Some(op)) => op,
-		(None, None) => panic!("No previous output provided in input."),
+	let (asset, change_value) = match tx.output.get(change_idx) {
+		Some(&TxOut {
+			asset: confidential::Asset::Explicit(asset),
+			value: confidential::Value::Explicit(value),
+			..
+		}) => (asset, value),
+		Some(_) => panic!("Change output {} must have an explicit asset and value.", change_idx),
+		None => panic!("Change output index {} out of range.", change_idx),
+	};
+	if asset != policy_asset {
+		panic!("Change output {} has asset {}, but the fee must be paid in the policy asset {}.",
+			change_idx, asset, policy_asset);
 	}
-}
 
-fn bytes_32(bytes: &[u8]) -> Option<[u8; 32]> {
-	if bytes.len() != 32 {
-		None
-	} else {
-		let mut array = [0; 32];
-		for (x, y) in bytes.iter().zip(array.iter_mut()) {
-			*y = *x;
-		}
-		Some(array)
+	// The fee output has a fixed size, so we can add it before knowing the fee.
+	tx.output.push(TxOut::new_fee(0, policy_asset));
+	let vsize = hal_elements::tx::vsize(tx.weight());
+	let fee = (fee_rate * vsize as f64).ceil() as u64;
+	if fee > change_value {
+		panic!("Change output value {} is not enough to pay the fee of {}.", change_value, fee);
 	}
+
+	tx.output[change_idx].value = confidential::Value::Explicit(change_value - fee);
+	tx.output.last_mut().unwrap().value = confidential::Value::Explicit(fee);
 }
 
+fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
+	let mut info: TransactionInfo = if let Some(path) = matches.value_of("json-file") {
+		let file = File::open(&path).expect("failed to open JSON file");
+		serde_json::from_reader(file).expect("invalid JSON")
+	} else {
+		let json_tx = matches.value_of("tx-info").expect("no JSON tx info provided");
+		serde_json::from_str(json_tx).expect("invalid JSON")
+	};
 
-fn create_confidential_value(info: ConfidentialValueInfo) -> confidential::Value {
-	match info.type_ {
-		ConfidentialType::Null => confidential::Value::Null,
-		ConfidentialType::Explicit => confidential::Value::Explicit(
-			info.value.expect("Field \"value\" is required for explicit values."),
-		),
-		ConfidentialType::Confidential => {
-			let comm = secp256k1_zkp::PedersenCommitment::from_slice(
-				info.commitment.expect("Missing commitment").bytes()
-			).expect("Invalid Confidential commitment");
-			confidential::Value::Confidential(comm)
+	if info.version.is_none() {
+		if let Some(version) = matches.value_of("default-version") {
+			info.version = Some(version.parse().expect("invalid default version"));
 		}
 	}
-}
-
-fn create_confidential_asset(info: ConfidentialAssetInfo) -> confidential::Asset {
-	match info.type_ {
-		ConfidentialType::Null => confidential::Asset::Null,
-		ConfidentialType::Explicit => confidential::Asset::Explicit(
-			info.asset.expect("Field \"asset\" is required for explicit assets."),
-		),
-		ConfidentialType::Confidential => {
-			let gen = secp256k1_zkp::Generator::from_slice(
-				info.commitment.expect("Missing asset commitment").bytes()
-			).expect("Invalid asset commitment");
-			confidential::Asset::Confidential(gen)
+	if info.locktime.is_none() {
+		if let Some(locktime) = matches.value_of("default-locktime") {
+			let locktime = locktime.parse::<u32>().expect("invalid default locktime");
+			info.locktime = Some(elements::LockTime::from_consensus(locktime));
 		}
 	}
-}
+	let mut network = cmd::network(matches);
+	let custom = match network {
+		Network::Custom(custom) => Some(custom),
+		_ => None,
+	};
+	let mut used_network = None;
+	let mut tx = match hal_elements::tx::create_transaction(info, custom, &mut used_network) {
+		Ok(tx) => tx,
+		Err(e) => panic!("Invalid transaction info: {}", e),
+	};
 
-fn create_confidential_nonce(info: ConfidentialNonceInfo) -> confidential::Nonce {
-	match info.type_ {
-		ConfidentialType::Null => confidential::Nonce::Null,
-		ConfidentialType::Explicit => confidential::Nonce::Explicit(
-			info.nonce.expect("Field \"nonce\" is required for explicit nonces.").to_byte_array(),
-		),
-		ConfidentialType::Confidential => {
-			let comm = secp256k1_zkp::PublicKey::from_slice(
-				info.commitment.expect("Missing nonce commitment").bytes()
-			).expect("Invalid Nonce Pubkey");
-			confidential::Nonce::Confidential(comm)
+	// The network is inferred from the output addresses, unless one is given.
+	let flag_given = [
+		"elementsregtest", "liquid", "liquidtestnet",
+		"bech-hrp", "blech-hrp", "genesis-hash", "policy-asset",
+	].iter().any(|f| matches.is_present(f));
+	if let Some(net) = used_network {
+		// A custom network can share the address params of another one.
+		if !flag_given {
+			network = net;
+		} else if net.address_params() != network.address_params() {
+			warn!("Output addresses are for {}, not for {} as given.", net.as_str(), network.as_str());
 		}
 	}
-}
 
-fn create_asset_issuance(info: AssetIssuanceInfo) -> AssetIssuance {
-	AssetIssuance {
-		asset_blinding_nonce: secp256k1_zkp::Tweak::from_slice(
-			&info
-				.asset_blinding_nonce
-				.expect("Field \"asset_blinding_nonce\" is required for asset issuances.")
-				.0[..],
-		)
-		.expect("Invalid size of \"asset_blinding_nonce\" or blinding nonce out of range"),
-		asset_entropy: bytes_32(
-			&info
-				.asset_entropy
-				.expect("Field \"asset_entropy\" is required for asset issuances.")
-				.0[..],
-		)
-		.expect("Invalid size of \"asset_entropy\"."),
-		amount: create_confidential_value(
-			info.amount.expect("Field \"amount\" is required for asset issuances."),
-		),
-		inflation_keys: create_confidential_value(
-			info.inflation_keys.expect("Field \"inflation_keys\" is required for asset issuances."),
-		),
+	if let Some(fee_rate) = matches.value_of("fee-rate") {
+		let fee_rate = fee_rate.parse::<f64>().expect("invalid fee rate");
+		let change_idx = matches.value_of("change-index").unwrap()
+			.parse::<usize>().expect("invalid change index");
+		add_fee_output(&mut tx, fee_rate, change_idx, network);
 	}
-}
 
-fn create_script_sig(ss: InputScriptInfo) -> Script {
-	if let Some(hex) = ss.hex {
-		if ss.asm.is_some() {
-			warn!("Field \"asm\" of input is ignored.");
-		}
+	if matches.is_present("sort-bip69") {
+		sort_bip69(&mut tx);
+	}
 
-		hex.0.into()
-	} else if let Some(_) = ss.asm {
-		panic!("Decoding script assembly is not yet supported.");
+	let tx_bytes = serialize(&tx);
+	if let Some(path) = matches.value_of("output") {
+		let mut file = File::create(&path).expect("failed to open output file");
+		file.write_all(&tx_bytes).expect("error writing output file");
+	} else if matches.is_present("raw-stdout") {
+		::std::io::stdout().write_all(&tx_bytes).unwrap();
 	} else {
-		panic!("No scriptSig info provided.");
+		print!("{}", hex::encode(&tx_bytes));
 	}
 }
 
-fn create_pegin_witness(pd: PeginDataInfo, prevout: bitcoin::OutPoint) -> Vec<Vec<u8>> {
-	let btc_prev = bitcoin::OutPoint::new(prevout.txid, prevout.vout);
-	if btc_prev != pd.outpoint.parse().expect("Invalid outpoint in field \"pegin_data\".") {
-		panic!("Outpoint in \"pegin_data\" does not correspond to input value.");
-	}
+fn cmd_decode<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("decode", "decode a raw transaction to JSON")
+		.args(&cmd::opts_networks())
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::opt_asset_labels(),
+			cmd::arg("raw-tx", "the raw transaction in hex, or `-` to read from stdin")
+				.required(false),
+			cmd::opt("txid-only", "only print the txid and wtxid").required(false),
+			cmd::opt("verify-proofs", "verify the output rangeproofs and show their minimum values")
+				.required(false),
+			cmd::opt("verbose-witness", "label the elements of pegin witnesses").required(false),
+			cmd::opt("no-witness", "decode the transaction with all witnesses removed")
+				.required(false),
+			cmd::opt("prevout", "an output spent by the tx as <txid>:<vout>:<raw-txout-hex>")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.required(false),
+		])
+}
 
-	let asset = match create_confidential_asset(pd.asset) {
-		confidential::Asset::Explicit(asset) => asset,
-		_ => panic!("Asset in \"pegin_data\" should be explicit."),
-	};
-	vec![
-		serialize(&pd.value),
-		serialize(&asset),
-		serialize(&pd.genesis_hash),
-		serialize(&pd.claim_script.0),
-		serialize(&pd.mainchain_tx_hex.0),
-		serialize(&pd.merkle_proof.0),
-	]
+/// Parse a spent output given as `<txid>:<vout>:<raw-txout-hex>`.
+fn parse_prevout(s: &str) -> (OutPoint, TxOut) {
+	let mut parts = s.splitn(3, ':');
+	let txid = parts.next().unwrap().parse().expect("invalid prevout txid");
+	let vout = parts.next().expect("prevout must be <txid>:<vout>:<raw-txout-hex>")
+		.parse().expect("invalid prevout vout");
+	let raw = hex::decode(parts.next().expect("prevout must be <txid>:<vout>:<raw-txout-hex>"))
+		.expect("invalid prevout txout hex");
+	(OutPoint::new(txid, vout), deserialize(&raw).expect("invalid prevout txout"))
 }
 
-fn create_input_witness(
-	info: Option<InputWitnessInfo>,
-	pd: Option<PeginDataInfo>,
-	prevout: bitcoin::OutPoint,
-) -> TxInWitness {
-	let pegin_witness = if info.is_some() && info.as_ref().unwrap().pegin_witness.is_some() {
-		if pd.is_some() {
-			warn!("Field \"pegin_data\" of input is ignored.");
-		}
-		info.as_ref().unwrap().pegin_witness.clone().unwrap().iter().map(|h| h.clone().0).collect()
-	} else if let Some(pd) = pd {
-		create_pegin_witness(pd, prevout)
-	} else {
-		Default::default()
-	};
+fn exec_decode<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = cmd::arg_or_stdin(matches, "raw-tx");
+	let raw_tx = hex::decode(hex_tx.as_ref()).expect("could not decode raw tx");
+	let mut tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
+	if matches.is_present("no-witness") {
+		// The txid doesn't commit to the witness, so it's the same as the wtxid of the result.
+		tx = hal_elements::tx::strip_witness(&tx);
+	}
 
-	if let Some(wi) = info {
-		TxInWitness {
-			amount_rangeproof: wi.amount_rangeproof
-				.map(|ref x| Box::new(RangeProof::from_slice(x.bytes()).expect("Invalid RangeProof"))),
-			inflation_keys_rangeproof: wi
-				.inflation_keys_rangeproof
-				.map(|ref x| Box::new(RangeProof::from_slice(x.bytes()).expect("Invalid RangeProof"))),
-			script_witness: match wi.script_witness {
-				Some(ref w) => w.iter().map(|h| h.clone().0).collect(),
-				None => Vec::new(),
-			},
-			pegin_witness: pegin_witness,
-		}
+	if matches.is_present("txid-only") {
+		let info = hal_elements::tx::TxidInfo::from_tx(&tx);
+		cmd::print_output(matches, &info)
 	} else {
-		TxInWitness {
-			pegin_witness: pegin_witness,
-			..Default::default()
+		let network = cmd::network(matches);
+		let mut info: TransactionInfo = ::GetInfo::get_info(&tx, network);
+		if matches.is_present("verify-proofs") {
+			let secp = secp256k1_zkp::Secp256k1::new();
+			let outputs = info.outputs.as_mut().expect("decoded tx has outputs");
+			for (out, txout) in outputs.iter_mut().zip(tx.output.iter()) {
+				out.rangeproof_valid = hal_elements::tx::verify_rangeproof(&secp, txout);
+				out.value = Some(hal_elements::tx::output_value_info(&secp, txout, network));
+			}
 		}
-	}
-}
-
-fn create_input(input: InputInfo) -> TxIn {
-	let has_issuance = input.has_issuance.unwrap_or(input.asset_issuance.is_some());
-	let is_pegin = input.is_pegin.unwrap_or(input.pegin_data.is_some());
-	let prevout = outpoint_from_input_info(&input);
-
-	TxIn {
-		previous_output: prevout,
-		script_sig: input.script_sig.map(create_script_sig).unwrap_or_default(),
-		sequence: input.sequence.unwrap_or_default(),
-		is_pegin: is_pegin,
-		asset_issuance: if has_issuance {
-			input.asset_issuance.map(create_asset_issuance).unwrap_or_default()
-		} else {
-			if input.asset_issuance.is_some() {
-				warn!("Field \"asset_issuance\" of input is ignored.");
+		if matches.is_present("verbose-witness") {
+			let inputs = info.inputs.as_mut().expect("decoded tx has inputs");
+			for (inp, txin) in inputs.iter_mut().zip(tx.input.iter()) {
+				if let Some(ref mut witness) = inp.witness {
+					hal_elements::tx::label_pegin_witness(witness, &txin.witness);
+				}
 			}
-			Default::default()
-		},
-		witness: {
-			let btc_prevout = bitcoin::OutPoint::new(
-				bitcoin::Txid::from_raw_hash(prevout.txid.into()),
-				 prevout.vout
-			);
-			create_input_witness(input.witness, input.pegin_data, btc_prevout)
 		}
+		if let Some(prevouts) = matches.values_of("prevout") {
+			let prevouts = prevouts.map(parse_prevout).collect::<Vec<_>>();
+			let spent = tx.input.iter().map(|i| {
+				prevouts.iter().find(|p| p.0 == i.previous_output).map(|p| p.1.clone())
+			}).collect::<Vec<_>>();
+			hal_elements::tx::set_spent_outputs(&mut info, &tx, &spent, network);
+		}
+		cmd::print_output_network(matches, &info, network)
 	}
 }
 
-fn create_pegout_script_pubkey(spk: hal::tx::OutputScriptInfo) -> bitcoin::ScriptBuf {
-	if spk.type_.is_some() {
-		warn!("Field \"type\" of output is ignored.");
-	}
-
-	if let Some(hex) = spk.hex {
-		if spk.asm.is_some() {
-			warn!("Field \"asm\" of output is ignored.");
-		}
-		if spk.address.is_some() {
-			warn!("Field \"address\" of output is ignored.");
-		}
+fn cmd_fee<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("fee", "compute the fee and virtual size of a raw transaction")
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::arg("raw-tx", "the raw transaction in hex").required(true),
+		])
+		.args(&cmd::opts_networks())
+}
 
-		//TODO(stevenroose) do script sanity check to avoid blackhole?
-		hex.0.into()
-	} else if let Some(_) = spk.asm {
-		if spk.address.is_some() {
-			warn!("Field \"address\" of output is ignored.");
-		}
+fn exec_fee<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = matches.value_of("raw-tx").expect("no raw tx provided");
+	let raw_tx = hex::decode(hex_tx).expect("could not decode raw tx");
+	let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
 
-		//TODO(stevenroose) support script disassembly
-		panic!("Decoding script assembly is not yet supported.");
-	} else if let Some(address) = spk.address {
-		address.assume_checked().script_pubkey()
-	} else {
-		panic!("No scriptPubKey info provided.");
-	}
+	let info = hal_elements::tx::fee_info(&tx, cmd::network(matches).policy_asset());
+	cmd::print_output(matches, &info)
 }
 
-fn create_script_pubkey(spk: OutputScriptInfo, used_network: &mut Option<Network>) -> Script {
-	if spk.type_.is_some() {
-		warn!("Field \"type\" of output is ignored.");
-	}
+fn cmd_sign<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("sign", "sign a p2pkh, p2wpkh or p2sh-p2wpkh input of a raw transaction")
+		.args(&[
+			cmd::arg("raw-tx", "the raw transaction in hex, or `-` to read from stdin")
+				.required(false),
+			cmd::opt("priv-key", "the private key in WIF/hex").takes_value(true).required(true),
+			cmd::opt("input-idx", "the index of the input to sign").takes_value(true).required(true),
+			cmd::opt("prevout", "the raw output spent by the input in hex")
+				.takes_value(true)
+				.required(true),
+			cmd::opt("sighash", "the sighash type to sign with")
+				.takes_value(true)
+				.possible_values(hal_elements::pset::sighashtype_values())
+				.default_value("ALL"),
+		])
+}
 
-	if let Some(hex) = spk.hex {
-		if spk.asm.is_some() {
-			warn!("Field \"asm\" of output is ignored.");
-		}
-		if spk.unblinded_address.is_some() {
-			warn!("Field \"address\" of output is ignored.");
-		}
+fn exec_sign<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = cmd::arg_or_stdin(matches, "raw-tx");
+	let raw_tx = hex::decode(hex_tx.as_ref()).expect("could not decode raw tx");
+	let mut tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
 
-		//TODO(stevenroose) do script sanity check to avoid blackhole?
-		hex.0.into()
-	} else if let Some(_) = spk.asm {
-		if spk.unblinded_address.is_some() {
-			warn!("Field \"address\" of output is ignored.");
-		}
+	let i = matches.value_of("input-idx").unwrap().parse::<usize>().expect("invalid input index");
+	if i >= tx.input.len() {
+		panic!("Input index {} out of range.", i);
+	}
+	let raw_prevout = hex::decode(matches.value_of("prevout").unwrap()).expect("invalid prevout hex");
+	let prevout: TxOut = deserialize(&raw_prevout).expect("invalid prevout");
+	let sighash_type = hal_elements::pset::sighashtype_from_string(matches.value_of("sighash").unwrap())
+		.unwrap_or_else(|e| panic!("{}", e));
+
+	let secp = secp256k1_zkp::Secp256k1::new();
+	let sk = cmd::pset::parse_priv_key(matches.value_of("priv-key").unwrap());
+	let pk = bitcoin::PublicKey::new(secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk));
+	let pk_bytes = pk.to_bytes();
+
+	// Segwit inputs sign the p2pkh script as script code.
+	let script_code = Script::new_p2pkh(&PubkeyHash::hash(&pk_bytes));
+	let wpkh = Script::new_v0_wpkh(&WPubkeyHash::hash(&pk_bytes));
+	let spk = &prevout.script_pubkey;
+	let (redeem_script, segwit) = if *spk == script_code {
+		(None, false)
+	} else if *spk == wpkh {
+		(None, true)
+	} else if *spk == Script::new_p2sh(&ScriptHash::hash(wpkh.as_bytes())) {
+		(Some(wpkh), true)
+	} else {
+		panic!("The prevout is not a p2pkh, p2wpkh or p2sh-p2wpkh output of the private key.");
+	};
 
-		panic!("Decoding script assembly is not yet supported.");
-	} else if let Some(address) = spk.unblinded_address {
-		// Error if another network had already been used.
-		let net = Network::from_params(address.params).expect("Unknown address");
-		if used_network.replace(net)
-			.unwrap_or(net) != net {
-			panic!("Addresses for different networks are used in the output scripts.");
+	let sighash = {
+		let mut cache = elements::sighash::SighashCache::new(&tx);
+		if segwit {
+			cache.segwitv0_sighash(i, &script_code, prevout.value, sighash_type)
+		} else {
+			cache.legacy_sighash(i, &script_code, sighash_type)
 		}
-
-		address.script_pubkey()
+	};
+	let msg = secp256k1_zkp::Message::from_slice(&sighash[..]).expect("sighash is 32 bytes");
+	let mut sig = secp.sign_ecdsa(&msg, &sk).serialize_der().to_vec();
+	sig.push(sighash_type.as_u32() as u8);
+
+	let input = &mut tx.input[i];
+	if segwit {
+		input.witness.script_witness = vec![sig, pk_bytes];
+		input.script_sig = match redeem_script {
+			Some(rs) => Builder::new().push_slice(rs.as_bytes()).into_script(),
+			None => Script::new(),
+		};
 	} else {
-		panic!("No scriptPubKey info provided.");
+		input.script_sig = Builder::new().push_slice(&sig).push_slice(&pk_bytes).into_script();
 	}
+	print!("{}", hex::encode(&serialize(&tx)));
 }
 
-fn create_output_witness(w: OutputWitnessInfo) -> TxOutWitness {
-	TxOutWitness {
-		surjection_proof: w.surjection_proof
-			.map(|ref x| Box::new(SurjectionProof::from_slice(x.bytes())
-			.expect("Invalid SurjectionProof"))),
-		rangeproof: w.rangeproof
-			.map(|ref x| Box::new(RangeProof::from_slice(x.bytes())
-			.expect("Invalid RangeProof"))),
-	}
+fn cmd_size<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("size", "print the size in bytes of a raw transaction").args(&[
+		cmd::arg("raw-tx", "the raw transaction in hex, or `-` to read from stdin").required(false),
+	])
 }
 
-// The network used is the bitcoin Network
-// so we do not maintain the network on the &mut used_network
-fn create_script_pubkey_from_pegout_data(
-	pd: PegoutDataInfo,
-) -> Script {
-	let mut builder = elements::script::Builder::new()
-		.push_opcode(elements::opcodes::all::OP_RETURN)
-		.push_slice(&pd.genesis_hash.to_byte_array())
-		.push_slice(&create_pegout_script_pubkey(pd.script_pub_key).as_bytes());
-	for d in pd.extra_data {
-		builder = builder.push_slice(&d.0);
-	}
-	builder.into_script()
-}
+fn exec_size<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = cmd::arg_or_stdin(matches, "raw-tx");
+	let raw_tx = hex::decode(hex_tx.as_ref()).expect("could not decode raw tx");
+	let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
 
-fn create_output(output: OutputInfo) -> TxOut {
-	// Keep track of which network has been used in addresses and error if two different networks
-	// are used.
-	let mut used_network = None;
-	let value = output
-		.value
-		.map(create_confidential_value)
-		.expect("Field \"value\" is required for outputs.");
-	let asset = output
-		.asset
-		.map(create_confidential_asset)
-		.expect("Field \"asset\" is required for outputs.");
-
-	TxOut {
-		asset: asset,
-		value: value,
-		nonce: output.nonce.map(create_confidential_nonce).unwrap_or(confidential::Nonce::Null),
-		script_pubkey: if let Some(spk) = output.script_pub_key {
-			if output.pegout_data.is_some() {
-				warn!("Field \"pegout_data\" of output is ignored.");
-			}
-			create_script_pubkey(spk, &mut used_network)
-		} else if let Some(pd) = output.pegout_data {
-			match value {
-				confidential::Value::Explicit(v) => {
-					if v != pd.value {
-						panic!("Value in \"pegout_data\" does not correspond to output value.");
-					}
-				}
-				_ => panic!("Explicit value is required for pegout data."),
-			}
-			if asset != create_confidential_asset(pd.asset.clone()) {
-				panic!("Asset in \"pegout_data\" does not correspond to output value.");
-			}
-			create_script_pubkey_from_pegout_data(pd)
-		} else {
-			Default::default()
-		},
-		witness: output.witness.map(create_output_witness).unwrap_or_default(),
-	}
+	println!("{}", serialize(&tx).len());
 }
 
-pub fn create_transaction(info: TransactionInfo) -> Transaction {
-	// Fields that are ignored.
-	if info.txid.is_some() {
-		warn!("Field \"txid\" is ignored.");
-	}
-	if info.hash.is_some() {
-		warn!("Field \"hash\" is ignored.");
-	}
-	if info.size.is_some() {
-		warn!("Field \"size\" is ignored.");
-	}
-	if info.weight.is_some() {
-		warn!("Field \"weight\" is ignored.");
-	}
-	if info.vsize.is_some() {
-		warn!("Field \"vsize\" is ignored.");
-	}
+fn cmd_verify<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("verify", "verify the script satisfaction of all inputs of a raw transaction")
+		.args(&[
+			cmd::opt_yaml(),
+			cmd::opt_cbor(),
+			cmd::arg("raw-tx", "the raw transaction in hex, or `-` to read from stdin")
+				.required(false),
+			cmd::opt("prevout", "an output spent by the tx as <txid>:<vout>:<raw-txout-hex>")
+				.takes_value(true)
+				.multiple(true)
+				.number_of_values(1)
+				.required(true),
+		])
+		.args(&cmd::opts_networks())
+}
 
-	Transaction {
-		version: info.version.expect("Field \"version\" is required."),
-		lock_time: info.locktime.expect("Field \"locktime\" is required."),
-		input: info
-			.inputs
-			.expect("Field \"inputs\" is required.")
-			.into_iter()
-			.map(create_input)
-			.collect(),
-		output: info
-			.outputs
-			.expect("Field \"outputs\" is required.")
-			.into_iter()
-			.map(create_output)
-			.collect(),
-	}
+/// The result of verifying the satisfaction of a single input.
+#[derive(Serialize)]
+struct InputVerifyInfo {
+	valid: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
 }
 
-fn exec_create<'a>(matches: &clap::ArgMatches<'a>) {
-	let json_tx = matches.value_of("tx-info").expect("no JSON tx info provided");
-	let info: TransactionInfo = serde_json::from_str(json_tx).expect("invalid JSON");
-	let tx = create_transaction(info);
+fn exec_verify<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = cmd::arg_or_stdin(matches, "raw-tx");
+	let raw_tx = hex::decode(hex_tx.as_ref()).expect("could not decode raw tx");
+	let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
 
-	let tx_bytes = serialize(&tx);
-	if matches.is_present("raw-stdout") {
-		::std::io::stdout().write_all(&tx_bytes).unwrap();
+	let prevouts = matches.values_of("prevout").unwrap().map(parse_prevout).collect::<Vec<_>>();
+	let spent = tx.input.iter().enumerate().map(|(i, txin)| {
+		prevouts.iter().find(|p| p.0 == txin.previous_output).map(|p| p.1.clone())
+			.unwrap_or_else(|| panic!("no prevout given for input {}", i))
+	}).collect::<Vec<_>>();
+	// The genesis hash is only committed to by taproot sighashes.
+	let genesis_hash = if spent.iter().any(|o| o.script_pubkey.is_v1_p2tr()) {
+		cmd::genesis_hash(matches)
 	} else {
-		print!("{}", hex::encode(&tx_bytes));
-	}
+		BlockHash::all_zeros()
+	};
+
+	let secp = secp256k1_zkp::Secp256k1::verification_only();
+	let all_prevouts = elements::sighash::Prevouts::All(&spent);
+	let info = tx.input.iter().enumerate().map(|(i, txin)| {
+		let interpreter = Interpreter::from_txdata(
+			&spent[i].script_pubkey,
+			&txin.script_sig,
+			&txin.witness.script_witness,
+			txin.sequence,
+			tx.lock_time,
+		);
+		let result = interpreter.map_err(|e| e.to_string()).and_then(|interpreter| {
+			for constraint in interpreter.iter(&secp, &tx, i, &all_prevouts, genesis_hash) {
+				constraint.map_err(|e| e.to_string())?;
+			}
+			Ok(())
+		});
+		match result {
+			Ok(()) => InputVerifyInfo { valid: true, error: None },
+			Err(e) => InputVerifyInfo { valid: false, error: Some(e) },
+		}
+	}).collect::<Vec<_>>();
+	cmd::print_output(matches, &info)
 }
 
-fn cmd_decode<'a>() -> clap::App<'a, 'a> {
-	cmd::subcommand("decode", "decode a raw transaction to JSON")
-		.args(&cmd::opts_networks())
-		.args(&[cmd::opt_yaml(), cmd::arg("raw-tx", "the raw transaction in hex").required(true)])
+fn cmd_weight<'a>() -> clap::App<'a, 'a> {
+	cmd::subcommand("weight", "print the weight in weight units of a raw transaction").args(&[
+		cmd::arg("raw-tx", "the raw transaction in hex, or `-` to read from stdin").required(false),
+	])
 }
 
-fn exec_decode<'a>(matches: &clap::ArgMatches<'a>) {
-	let hex_tx = matches.value_of("raw-tx").expect("no raw tx provided");
-	let raw_tx = hex::decode(hex_tx).expect("could not decode raw tx");
+fn exec_weight<'a>(matches: &clap::ArgMatches<'a>) {
+	let hex_tx = cmd::arg_or_stdin(matches, "raw-tx");
+	let raw_tx = hex::decode(hex_tx.as_ref()).expect("could not decode raw tx");
 	let tx: Transaction = deserialize(&raw_tx).expect("invalid tx format");
 
-	let info = ::GetInfo::get_info(&tx, cmd::network(matches));
-	cmd::print_output(matches, &info)
+	println!("{}", tx.weight());
 }
@@ -4,6 +4,7 @@ extern crate clap;
 extern crate elements;
 extern crate fern;
 extern crate hex;
+extern crate serde;
 extern crate serde_json;
 
 extern crate hal;
@@ -51,6 +52,12 @@ fn init_app<'a, 'b>() -> clap::App<'a, 'b> {
 				.takes_value(false)
 				.global(true),
 		)
+		.arg(
+			cmd::opt("btc-amounts", "also show explicit values of the policy asset in whole coins")
+				.takes_value(false)
+				.global(true),
+		)
+		.args(&cmd::opts_indent())
 }
 
 /// Try execute built-in command. Return false if no command found.
@@ -58,6 +65,7 @@ fn execute_builtin<'a>(matches: &clap::ArgMatches<'a>) -> bool {
 	match matches.subcommand() {
 		("address", Some(ref m)) => cmd::address::execute(&m),
 		("block", Some(ref m)) => cmd::block::execute(&m),
+		("confidential", Some(ref m)) => cmd::confidential::execute(&m),
 		("tx", Some(ref m)) => cmd::tx::execute(&m),
 		("miniscript", Some(ref m)) => cmd::miniscript::execute(&m),
 		("pset", Some(ref m)) => cmd::pset::execute(&m),
@@ -67,8 +75,8 @@ fn execute_builtin<'a>(matches: &clap::ArgMatches<'a>) -> bool {
 }
 
 fn main() {
-	// Apply a custom panic hook to print a more user-friendly message
-	// in case the execution fails.
+	// Apply a custom panic hook to print a JSON error object to stderr
+	// in case the execution fails, so that it can be parsed by scripts.
 	panic::set_hook(Box::new(|info| {
 		let message = if let Some(m) = info.payload().downcast_ref::<String>() {
 			m
@@ -77,7 +85,9 @@ fn main() {
 		} else {
 			"No error message provided"
 		};
-		println!("Execution failed: {}", message);
+		let mut error = serde_json::Map::new();
+		error.insert("error".to_owned(), message.to_owned().into());
+		eprintln!("{}", serde_json::Value::Object(error));
 		process::exit(1);
 	}));
 
@@ -1,5 +1,8 @@
-use elements::hashes::{sha256, Hash};
-use elements::{BlockHash, TxMerkleNode, Txid};
+use std::collections::BTreeMap;
+
+use elements::encode::serialize;
+use elements::hashes::{sha256, sha256d, Hash, HashEngine};
+use elements::{AssetId, BlockHash, Transaction, TxMerkleNode, Txid};
 use elements::{dynafed, Block, BlockExtData, BlockHeader};
 use serde::{Deserialize, Serialize};
 
@@ -69,7 +72,9 @@ pub struct BlockHeaderInfo {
 	pub block_hash: Option<BlockHash>,
 	pub version: u32,
 	pub previous_block_hash: BlockHash,
-	pub merkle_root: TxMerkleNode,
+	/// Computed from the transactions when omitted on block creation.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub merkle_root: Option<TxMerkleNode>,
 	pub time: u32,
 	pub height: u32,
 	pub dynafed: bool,
@@ -89,7 +94,7 @@ impl Default for BlockHeaderInfo {
     fn default() -> Self {
         Self {
 			previous_block_hash: BlockHash::all_zeros(),
-			merkle_root: TxMerkleNode::all_zeros(),
+			merkle_root: None,
             block_hash: None,
             version: 0,
             time: 0,
@@ -110,7 +115,7 @@ impl<'a> GetInfo<BlockHeaderInfo> for BlockHeader {
 			block_hash: Some(self.block_hash()),
 			version: self.version,
 			previous_block_hash: self.prev_blockhash,
-			merkle_root: self.merkle_root,
+			merkle_root: Some(self.merkle_root),
 			time: self.time,
 			height: self.height,
 			..Default::default()
@@ -150,15 +155,118 @@ pub struct BlockInfo {
 	pub txids: Option<Vec<Txid>>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub raw_transactions: Option<Vec<HexBytes>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tx_count: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_size: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_weight: Option<usize>,
+	/// The sum of the fees of all transactions per fee asset.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub total_fee: Option<BTreeMap<AssetId, u64>>,
+}
+
+/// Add the fees paid by the transaction to the per-asset totals.
+pub fn add_fees(total_fee: &mut BTreeMap<AssetId, u64>, tx: &Transaction, network: Network) {
+	for out in tx.output.iter().filter(|o| ::tx::is_fee_output(o, network.policy_asset())) {
+		if let (Some(asset), Some(value)) = (out.asset.explicit(), out.value.explicit()) {
+			*total_fee.entry(asset).or_insert(0) += value;
+		}
+	}
+}
+
+impl BlockInfo {
+	/// Get the info of the block with only the txids instead of the full transactions.
+	pub fn summary(block: &Block, network: Network) -> BlockInfo {
+		let mut info = BlockInfo {
+			header: block.header.get_info(network),
+			transactions: None,
+			txids: Some(block.txdata.iter().map(|t| t.txid()).collect()),
+			raw_transactions: None,
+			tx_count: None,
+			total_size: None,
+			total_weight: None,
+			total_fee: None,
+		};
+		info.set_stats(block, network);
+		info
+	}
+
+	fn set_stats(&mut self, block: &Block, network: Network) {
+		let size = serialize(block).len();
+		let txs_size: usize = block.txdata.iter().map(|t| t.size()).sum();
+		let txs_weight: usize = block.txdata.iter().map(|t| t.weight()).sum();
+
+		self.tx_count = Some(block.txdata.len());
+		self.total_size = Some(size);
+		self.total_weight = Some((size - txs_size) * 4 + txs_weight);
+		let mut total_fee = BTreeMap::new();
+		for tx in block.txdata.iter().filter(|t| !t.is_coinbase()) {
+			add_fees(&mut total_fee, tx, network);
+		}
+		self.total_fee = Some(total_fee);
+	}
 }
 
 impl GetInfo<BlockInfo> for Block {
 	fn get_info(&self, network: Network) -> BlockInfo {
-		BlockInfo {
+		let mut info = BlockInfo {
 			header: self.header.get_info(network),
 			transactions: Some(self.txdata.iter().map(|t| t.get_info(network)).collect()),
 			txids: None,
 			raw_transactions: None,
+			tx_count: None,
+			total_size: None,
+			total_weight: None,
+			total_fee: None,
+		};
+		info.set_stats(self, network);
+		info
+	}
+}
+
+/// The number of transactions, inputs, outputs and issuances in a block.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct BlockCountInfo {
+	pub tx_count: usize,
+	pub input_count: usize,
+	pub output_count: usize,
+	pub issuance_count: usize,
+}
+
+impl BlockCountInfo {
+	pub fn from_block(block: &Block) -> BlockCountInfo {
+		let inputs = block.txdata.iter().flat_map(|t| t.input.iter());
+		BlockCountInfo {
+			tx_count: block.txdata.len(),
+			input_count: block.txdata.iter().map(|t| t.input.len()).sum(),
+			output_count: block.txdata.iter().map(|t| t.output.len()).sum(),
+			issuance_count: inputs.filter(|i| i.has_issuance()).count(),
+		}
+	}
+}
+
+/// Compute the merkle root of the txids of the given transactions.
+pub fn merkle_root(txdata: &[Transaction]) -> TxMerkleNode {
+	if txdata.is_empty() {
+		return TxMerkleNode::all_zeros();
+	}
+
+	let mut hashes: Vec<sha256d::Hash> = txdata.iter().map(|t| t.txid().to_raw_hash()).collect();
+	while hashes.len() > 1 {
+		if hashes.len() % 2 == 1 {
+			let last = *hashes.last().unwrap();
+			hashes.push(last);
 		}
+		hashes = hashes
+			.chunks(2)
+			.map(|pair| {
+				let mut engine = sha256d::Hash::engine();
+				engine.input(&pair[0][..]);
+				engine.input(&pair[1][..]);
+				sha256d::Hash::from_engine(engine)
+			})
+			.collect();
 	}
+	TxMerkleNode::from_raw_hash(hashes[0])
 }
@@ -1,9 +1,10 @@
 use bitcoin::hashes::sha256d;
 use elements::{confidential::{Asset, Nonce, Value}, hashes::Hash};
-use elements::AssetId;
-use serde::{Deserialize, Serialize};
+use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+use elements::{secp256k1_zkp, slip77, AssetId, Script, TxOut, TxOutSecrets, TxOutWitness};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use ::{GetInfo, Network, HexBytes};
+use ::{Error, GetInfo, Network, HexBytes};
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -21,6 +22,9 @@ pub struct ConfidentialValueInfo {
 	pub value: Option<u64>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub commitment: Option<HexBytes>,
+	/// The minimum value proven by the rangeproof, see [value_info_with_rangeproof].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub min_value: Option<u64>,
 }
 
 impl GetInfo<ConfidentialValueInfo> for Value {
@@ -41,27 +45,87 @@ impl GetInfo<ConfidentialValueInfo> for Value {
 				}
 				_ => None,
 			},
+			min_value: None,
 		}
 	}
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "snake_case")]
+/// Get the info of a value, including the minimum value proven by its rangeproof if it is
+/// confidential.
+///
+/// The rangeproof is verified against the asset generator and the message it commits to,
+/// which is the scriptPubKey for transaction outputs.
+pub fn value_info_with_rangeproof<C: secp256k1_zkp::Verification>(
+	value: &Value,
+	network: Network,
+	secp: &secp256k1_zkp::Secp256k1<C>,
+	rangeproof: &secp256k1_zkp::RangeProof,
+	generator: secp256k1_zkp::Generator,
+	message: &[u8],
+) -> ConfidentialValueInfo {
+	let mut info = value.get_info(network);
+	if let Value::Confidential(commitment) = *value {
+		info.min_value = rangeproof.verify(secp, commitment, message, generator).ok().map(|r| r.start);
+	}
+	info
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum ConfidentialAssetLabel {
 	LiquidBitcoin,
+	LiquidTestnetBitcoin,
+	/// The policy asset of an elementsregtest chain with the default parameters.
+	RegtestBitcoin,
+	/// The policy asset of a custom network.
+	PolicyAsset,
+	/// Any other label, such as a custom label given by the user.
+	Other(String),
 }
 
 impl ConfidentialAssetLabel {
-	pub fn from_asset_id(id: elements::AssetId) -> Option<ConfidentialAssetLabel> {
-		match id.to_string().as_str() {
-			"6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d" => {
-				Some(ConfidentialAssetLabel::LiquidBitcoin)
-			}
-			_ => None,
+	/// Get the label of the asset id, only labeling the policy asset of the given network.
+	pub fn from_asset_id(id: elements::AssetId, network: Network) -> Option<ConfidentialAssetLabel> {
+		if Some(id) != network.policy_asset() {
+			return None;
+		}
+		match network {
+			Network::Liquid => Some(ConfidentialAssetLabel::LiquidBitcoin),
+			Network::LiquidTestnet => Some(ConfidentialAssetLabel::LiquidTestnetBitcoin),
+			Network::ElementsRegtest => Some(ConfidentialAssetLabel::RegtestBitcoin),
+			Network::Custom(_) => Some(ConfidentialAssetLabel::PolicyAsset),
+		}
+	}
+
+	pub fn as_str(&self) -> &str {
+		match *self {
+			ConfidentialAssetLabel::LiquidBitcoin => "liquid_bitcoin",
+			ConfidentialAssetLabel::LiquidTestnetBitcoin => "liquid_testnet_bitcoin",
+			ConfidentialAssetLabel::RegtestBitcoin => "regtest_bitcoin",
+			ConfidentialAssetLabel::PolicyAsset => "policy_asset",
+			ConfidentialAssetLabel::Other(ref label) => label,
 		}
 	}
 }
 
+impl Serialize for ConfidentialAssetLabel {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.as_str())
+	}
+}
+
+impl<'de> Deserialize<'de> for ConfidentialAssetLabel {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let label = String::deserialize(deserializer)?;
+		Ok(match label.as_str() {
+			"liquid_bitcoin" => ConfidentialAssetLabel::LiquidBitcoin,
+			"liquid_testnet_bitcoin" => ConfidentialAssetLabel::LiquidTestnetBitcoin,
+			"regtest_bitcoin" => ConfidentialAssetLabel::RegtestBitcoin,
+			"policy_asset" => ConfidentialAssetLabel::PolicyAsset,
+			_ => ConfidentialAssetLabel::Other(label),
+		})
+	}
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct ConfidentialAssetInfo {
 	#[serde(rename = "type")]
@@ -75,7 +139,7 @@ pub struct ConfidentialAssetInfo {
 }
 
 impl GetInfo<ConfidentialAssetInfo> for Asset {
-	fn get_info(&self, _network: Network) -> ConfidentialAssetInfo {
+	fn get_info(&self, network: Network) -> ConfidentialAssetInfo {
 		ConfidentialAssetInfo {
 			type_: match self {
 				Asset::Null => ConfidentialType::Null,
@@ -93,7 +157,7 @@ impl GetInfo<ConfidentialAssetInfo> for Asset {
 				_ => None,
 			},
 			label: match self {
-				Asset::Explicit(a) => ConfidentialAssetLabel::from_asset_id(*a),
+				Asset::Explicit(a) => ConfidentialAssetLabel::from_asset_id(*a, network),
 				_ => None,
 			},
 		}
@@ -131,3 +195,60 @@ impl GetInfo<ConfidentialNonceInfo> for Nonce {
 		}
 	}
 }
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct TxOutSecretsInfo {
+	pub value: u64,
+	pub asset: AssetId,
+	pub value_blinder: ValueBlindingFactor,
+	pub asset_blinder: AssetBlindingFactor,
+}
+
+impl GetInfo<TxOutSecretsInfo> for TxOutSecrets {
+	fn get_info(&self, _network: Network) -> TxOutSecretsInfo {
+		TxOutSecretsInfo {
+			value: self.value,
+			asset: self.asset,
+			value_blinder: self.value_bf,
+			asset_blinder: self.asset_bf,
+		}
+	}
+}
+
+/// Unblind a confidential output with its blinding secret key.
+///
+/// The rangeproof in the given witness is rewound, so the witness of the output itself is
+/// ignored. Returns the value, the asset id and both blinding factors.
+pub fn unblind_txout(
+	txout: &TxOut,
+	witness: &TxOutWitness,
+	blinding_secret: &secp256k1_zkp::SecretKey,
+) -> Result<TxOutSecrets, Error> {
+	let txout = TxOut {
+		witness: witness.clone(),
+		..txout.clone()
+	};
+	let secp = secp256k1_zkp::Secp256k1::new();
+	txout.unblind(&secp, *blinding_secret).map_err(|e| Error::Unblind(e.to_string()))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct BlindingKeyInfo {
+	pub secret_key: HexBytes,
+	pub public_key: secp256k1_zkp::PublicKey,
+}
+
+impl BlindingKeyInfo {
+	/// Derive the blinding key of the scriptPubKey from the SLIP-77 master blinding key.
+	pub fn from_slip77(
+		master_blinding_key: &slip77::MasterBlindingKey,
+		script_pubkey: &Script,
+	) -> BlindingKeyInfo {
+		let secp = secp256k1_zkp::Secp256k1::signing_only();
+		let sk = master_blinding_key.blinding_private_key(script_pubkey);
+		BlindingKeyInfo {
+			secret_key: sk.secret_bytes().to_vec().into(),
+			public_key: secp256k1_zkp::PublicKey::from_secret_key(&secp, &sk),
+		}
+	}
+}
@@ -1,3 +1,5 @@
+#[macro_use]
+extern crate log;
 extern crate elements;
 extern crate hal;
 extern crate hex;
@@ -16,15 +18,65 @@ pub mod confidential;
 pub use hal::HexBytes;
 pub use elements::bitcoin;
 
-use elements::AddressParams;
-use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use elements::{AddressParams, AssetId, BlockHash};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Errors that can occur in the library.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+	/// A required field was not provided.
+	MissingField(&'static str),
+	/// A field has an invalid value.
+	InvalidField(&'static str, String),
+	/// Fields have values that conflict with each other.
+	Conflict(String),
+	/// An error in the input with the given index.
+	Input(usize, Box<Error>),
+	/// An error in the output with the given index.
+	Output(usize, Box<Error>),
+	/// A confidential output could not be unblinded.
+	Unblind(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::MissingField(field) => write!(f, "Field \"{}\" is required.", field),
+			Error::InvalidField(field, ref reason) => {
+				write!(f, "Invalid field \"{}\": {}", field, reason)
+			}
+			Error::Conflict(ref msg) => f.write_str(msg),
+			Error::Input(idx, ref e) => write!(f, "Input {}: {}", idx, e),
+			Error::Output(idx, ref e) => write!(f, "Output {}: {}", idx, e),
+			Error::Unblind(ref msg) => write!(f, "Failed to unblind: {}", msg),
+		}
+	}
+}
+
+impl ::std::error::Error for Error {}
 
 /// Known Elements networks.
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Network {
 	ElementsRegtest,
 	Liquid,
+	LiquidTestnet,
+	/// A custom Elements chain with the given parameters.
+	Custom(&'static CustomNetwork),
+}
+
+/// The parameters of a custom Elements chain.
+///
+/// Addresses keep a static reference to their params, so a custom network has to be static too.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CustomNetwork {
+	pub address_params: AddressParams,
+	/// The genesis block hash, if known.
+	pub genesis_hash: Option<BlockHash>,
+	/// The policy asset, if known.
+	pub policy_asset: Option<AssetId>,
 }
 
 impl Network {
@@ -32,14 +84,92 @@ impl Network {
 		match params {
 			&AddressParams::ELEMENTS => Some(Network::ElementsRegtest),
 			&AddressParams::LIQUID => Some(Network::Liquid),
+			&AddressParams::LIQUID_TESTNET => Some(Network::LiquidTestnet),
 			_ => None,
 		}
 	}
 
+	/// Like [Network::from_params], but the params of the custom network are detected first.
+	pub fn from_params_with_custom(
+		params: &'static AddressParams,
+		custom: &'static CustomNetwork,
+	) -> Option<Network> {
+		if *params == custom.address_params {
+			Some(Network::Custom(custom))
+		} else {
+			Network::from_params(params)
+		}
+	}
+
 	pub fn address_params(self) -> &'static AddressParams {
 		match self {
 			Network::ElementsRegtest => &AddressParams::ELEMENTS,
 			Network::Liquid => &AddressParams::LIQUID,
+			Network::LiquidTestnet => &AddressParams::LIQUID_TESTNET,
+			Network::Custom(custom) => &custom.address_params,
+		}
+	}
+
+	/// The policy asset of the network, in which fees are paid.
+	///
+	/// For elementsregtest, this is the policy asset of a chain with the default parameters,
+	/// which can be overridden with a custom network. For a custom network, it's only known
+	/// if it has been given.
+	pub fn policy_asset(self) -> Option<AssetId> {
+		let hex = match self {
+			Network::ElementsRegtest => {
+				"b2e15d0d7a0c94e4e2ce0fe6e8691b9e451377f6e46e8045a86f7c4b5d4f0f23"
+			}
+			Network::Liquid => "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d",
+			Network::LiquidTestnet => {
+				"144c654344aa716d6f3abcc1ca90e5641e4e2a7f633bc09fe3baf64585819a49"
+			}
+			Network::Custom(custom) => return custom.policy_asset,
+		};
+		Some(hex.parse().expect("valid policy asset id"))
+	}
+
+	/// The genesis block hash of the chain, which is committed to by taproot sighashes.
+	///
+	/// It's not known for elementsregtest, because it depends on the chain parameters.
+	pub fn genesis_hash(self) -> Option<BlockHash> {
+		let hex = match self {
+			Network::ElementsRegtest => return None,
+			Network::Liquid => "1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003",
+			Network::LiquidTestnet => {
+				"a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1"
+			}
+			Network::Custom(custom) => return custom.genesis_hash,
+		};
+		Some(hex.parse().expect("valid genesis hash"))
+	}
+
+	/// The name of the network, as used in JSON.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Network::ElementsRegtest => "elementsregtest",
+			Network::Liquid => "liquid",
+			Network::LiquidTestnet => "liquidtestnet",
+			Network::Custom(_) => "custom",
+		}
+	}
+}
+
+impl Serialize for Network {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.as_str())
+	}
+}
+
+/// Custom networks can't be deserialized, because their parameters are not serialized.
+impl<'de> Deserialize<'de> for Network {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let name = String::deserialize(deserializer)?;
+		match name.as_str() {
+			"elementsregtest" => Ok(Network::ElementsRegtest),
+			"liquid" => Ok(Network::Liquid),
+			"liquidtestnet" => Ok(Network::LiquidTestnet),
+			_ => Err(::serde::de::Error::custom(format!("unknown network: {}", name))),
 		}
 	}
 }
@@ -49,3 +179,36 @@ pub trait GetInfo<T: ::serde::Serialize> {
 	/// Get a description of this object given the network of interest.
 	fn get_info(&self, network: Network) -> T;
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn network_params_roundtrip() {
+		for network in &[Network::ElementsRegtest, Network::Liquid, Network::LiquidTestnet] {
+			let params = network.address_params();
+			assert_eq!(Network::from_params(params), Some(*network));
+			assert_eq!(Network::from_params(params).unwrap().address_params(), params);
+		}
+
+		let custom: &'static CustomNetwork = Box::leak(Box::new(CustomNetwork {
+			address_params: AddressParams {
+				bech_hrp: "cus",
+				blech_hrp: "cusb",
+				..AddressParams::ELEMENTS
+			},
+			genesis_hash: None,
+			policy_asset: None,
+		}));
+		let network = Network::Custom(custom);
+		let params = network.address_params();
+		assert_eq!(Network::from_params(params), None);
+		assert_eq!(Network::from_params_with_custom(params, custom), Some(network));
+		assert_eq!(network.address_params(), &custom.address_params);
+
+		// The builtin networks are still detected next to a custom one.
+		let liquid = Network::from_params_with_custom(&AddressParams::LIQUID, custom);
+		assert_eq!(liquid, Some(Network::Liquid));
+	}
+}
@@ -77,6 +77,7 @@ pub struct DescriptorInfo {
 	pub policy: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub descriptor_info: Option<String>,
+	pub sane: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
@@ -97,6 +98,10 @@ pub struct MiniscriptInfo {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub max_satisfaction_size_non_segwit: Option<usize>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_satisfaction_weight_segwit: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_satisfaction_weight_non_segwit: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub script: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub policy: Option<String>,
@@ -117,3 +122,36 @@ pub struct PolicyInfo {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub miniscript: Option<Miniscripts>,
 }
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct CompilationInfo {
+	pub key_type: MiniscriptKeyType,
+	pub miniscript: String,
+	pub descriptor: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_satisfaction_weight: Option<usize>,
+}
+
+/// A script lifted into miniscript, in each script context it is valid in.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct LiftInfo {
+	pub miniscript: Miniscripts,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub policy: Option<String>,
+}
+
+/// A single way to satisfy a policy, with everything it requires.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct SpendingPathInfo {
+	pub keys: Vec<String>,
+	pub hashes: Vec<String>,
+	pub relative_timelocks: Vec<u32>,
+	pub absolute_timelocks: Vec<u32>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct SpendingPathsInfo {
+	pub key_type: MiniscriptKeyType,
+	pub policy: String,
+	pub paths: Vec<SpendingPathInfo>,
+}
@@ -1,14 +1,17 @@
 use elements::pset::PsbtSighashType;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use elements::locktime::LockTime;
 
 use bitcoin::bip32;
-use elements::{EcdsaSighashType, confidential};
-use elements::hashes::Hash;
+use elements::{AssetId, ContractHash, EcdsaSighashType, confidential};
+use elements::hashes::{sha256, Hash};
+use elements::secp256k1_zkp;
+use elements::schnorr::SchnorrSig;
+use elements::taproot::{ControlBlock, LeafVersion, TapLeafHash, TapNodeHash};
 use elements::{pset, encode};
-use Network;
+use {Error, Network};
 
 use hal::HexBytes;
 
@@ -18,17 +21,21 @@ pub struct PsetGlobalInfo {
 	pub tx_version: u32,
 	pub num_inputs: u32,
 	pub num_outputs: u32,
-	pub fallback_locktime: LockTime,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fallback_locktime: Option<LockTime>,
 	pub tx_modifiable: u8,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub xpub: HashMap<String, String>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub xpub: BTreeMap<String, String>,
 	#[serde(skip_serializing_if = "Vec::is_empty")]
 	pub scalars: Vec<::HexBytes>,
 	pub elements_tx_modifiable_flag: u8,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub proprietary: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub unknown: HashMap<::HexBytes, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub proprietary: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub unknown: BTreeMap<String, ::HexBytes>,
+	/// The unsigned transaction described by the PSET, see [unsigned_tx_info].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub unsigned_tx: Option<::tx::TransactionInfo>,
 }
 
 impl ::GetInfo<PsetGlobalInfo> for pset::Global {
@@ -38,10 +45,10 @@ impl ::GetInfo<PsetGlobalInfo> for pset::Global {
 		    tx_version: self.tx_data.version,
 		    num_inputs: self.n_inputs() as u32,
 		    num_outputs: self.n_outputs() as u32,
-		    fallback_locktime: self.tx_data.fallback_locktime.unwrap_or(LockTime::ZERO),
+		    fallback_locktime: self.tx_data.fallback_locktime,
 		    tx_modifiable: self.tx_data.tx_modifiable.unwrap_or(0),
 		    xpub: {
-				let mut xpubs = HashMap::new();
+				let mut xpubs = BTreeMap::new();
 				for (k, (f, d)) in &self.xpub {
 					let x = format!("({},{})", f.to_string(), d.to_string());
 					xpubs.insert(k.to_string(), x);
@@ -57,62 +64,88 @@ impl ::GetInfo<PsetGlobalInfo> for pset::Global {
 			},
 		    elements_tx_modifiable_flag: self.elements_tx_modifiable_flag.unwrap_or(0),
 		    proprietary: {
-				let mut proprietary = HashMap::new();
+				let mut proprietary = BTreeMap::new();
 				for (k, v) in &self.proprietary {
 					proprietary.insert(
-						HexBytes::from(encode::serialize(&k.to_key())),
+						hex::encode(encode::serialize(&k.to_key())),
 						HexBytes::from(v.to_vec()),
 					);
 				}
 				proprietary
 			},
 		    unknown: {
-				let mut unknown = HashMap::new();
+				let mut unknown = BTreeMap::new();
 				for (k, v) in &self.unknown {
 					unknown.insert(
-						HexBytes::from(encode::serialize(k)),
+						hex::encode(encode::serialize(k)),
 						HexBytes::from(v.to_vec()),
 					);
 				}
 				unknown
 			},
+			unsigned_tx: None,
 		}
 	}
 }
 
+/// Get the info of the unsigned transaction described by the PSET, with empty scriptSigs and
+/// witnesses.
+///
+/// Returns `None` if the transaction can't be extracted from the PSET.
+pub fn unsigned_tx_info(
+	pset: &pset::PartiallySignedTransaction,
+	network: Network,
+) -> Option<::tx::TransactionInfo> {
+	let mut tx = pset.extract_tx().ok()?;
+	for input in tx.input.iter_mut() {
+		input.script_sig = Default::default();
+		input.witness.script_witness = Vec::new();
+		input.witness.pegin_witness = Vec::new();
+	}
+	Some(::GetInfo::get_info(&tx, network))
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct HDPathInfo {
 	pub master_fingerprint: bip32::Fingerprint,
 	pub path: bip32::DerivationPath,
 }
 
-pub fn sighashtype_to_string(sht: PsbtSighashType) -> String {
+pub fn sighashtype_to_string(sht: PsbtSighashType) -> Result<String, Error> {
 	use elements::EcdsaSighashType::*;
-	match sht.ecdsa_hash_ty().expect("Taproot sighash not yet supported") {
+	let ecdsa = sht.ecdsa_hash_ty().map_err(|_| {
+		Error::InvalidField("sighash_type", format!("non-standard SIGHASH type: {:#x}", sht.to_u32()))
+	})?;
+	Ok(match ecdsa {
 		All => "ALL",
 		None => "NONE",
 		Single => "SINGLE",
 		AllPlusAnyoneCanPay => "ALL|ANYONECANPAY",
 		NonePlusAnyoneCanPay => "NONE|ANYONECANPAY",
 		SinglePlusAnyoneCanPay => "SINGLE|ANYONECANPAY",
-	}.to_owned()
+	}.to_owned())
 }
 
 pub fn sighashtype_values() -> &'static [&'static str] {
 	&["ALL", "NONE", "SINGLE", "ALL|ANYONECANPAY", "NONE|ANYONECANPAY", "SINGLE|ANYONECANPAY"]
 }
 
-pub fn sighashtype_from_string(sht: &str) -> EcdsaSighashType {
+pub fn sighashtype_from_string(sht: &str) -> Result<EcdsaSighashType, Error> {
 	use elements::EcdsaSighashType::*;
-	match sht {
+	Ok(match sht {
 		"ALL" => All,
 		"NONE" => None,
 		"SINGLE" => Single,
 		"ALL|ANYONECANPAY" => AllPlusAnyoneCanPay,
 		"NONE|ANYONECANPAY" => NonePlusAnyoneCanPay,
 		"SINGLE|ANYONECANPAY" => SinglePlusAnyoneCanPay,
-		_ => panic!("invalid SIGHASH type value -- possible values: {:?}", &sighashtype_values()),
-	}
+		_ => {
+			return Err(Error::InvalidField(
+				"sighash_type",
+				format!("possible values: {:?}", sighashtype_values()),
+			))
+		}
+	})
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
@@ -121,31 +154,32 @@ pub struct PsetInputInfo {
 	pub non_witness_utxo: Option<::tx::TransactionInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub witness_utxo: Option<::tx::OutputInfo>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub partial_sigs: HashMap<::HexBytes, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub partial_sigs: BTreeMap<String, ::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub sighash_type: Option<String>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub redeem_script: Option<::tx::OutputScriptInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub witness_script: Option<::tx::OutputScriptInfo>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub hd_keypaths: HashMap<::HexBytes, HDPathInfo>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub hd_keypaths: BTreeMap<String, HDPathInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub final_script_sig: Option<::tx::InputScriptInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub final_script_witness: Option<Vec<::HexBytes>>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub ripemd160_preimages: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub sha256_preimages: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub hash160_preimages: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub hash256_preimages: HashMap<::HexBytes, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub ripemd160_preimages: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sha256_preimages: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hash160_preimages: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hash256_preimages: BTreeMap<String, ::HexBytes>,
     pub previous_txid: ::HexBytes,
     pub previous_output_index: u32,
-    pub sequence: elements::Sequence,
+	#[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<elements::Sequence>,
 	#[serde(skip_serializing_if = "Option::is_none")]
     pub required_time_locktime: Option<elements::locktime::Time>,
 	#[serde(skip_serializing_if = "Option::is_none")]
@@ -178,33 +212,127 @@ pub struct PsetInputInfo {
     pub issuance_blinding_nonce: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
     pub issuance_asset_entropy: Option<::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub proprietary: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub unknown: HashMap<::HexBytes, ::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub issuance_asset_id: Option<AssetId>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub issuance_token_id: Option<AssetId>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tap_key_sig: Option<::HexBytes>,
+	/// Keyed by the x-only public key followed by the leaf hash.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub tap_script_sigs: BTreeMap<String, ::HexBytes>,
+	/// Keyed by the control block.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub tap_scripts: BTreeMap<String, TapScriptInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tap_internal_key: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tap_merkle_root: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub proprietary: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub unknown: BTreeMap<String, ::HexBytes>,
+	pub finalized: bool,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub missing: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct TapScriptInfo {
+	pub script: ::HexBytes,
+	pub leaf_version: u8,
+}
+
+/// Lists the fields that are still needed before the input can be finalized.
+fn missing_input_fields(input: &pset::Input) -> Vec<String> {
+	let mut missing = Vec::new();
+	let spk = match (&input.witness_utxo, &input.non_witness_utxo) {
+		(&Some(ref utxo), _) => Some(utxo.script_pubkey.clone()),
+		(&None, &Some(ref tx)) => {
+			tx.output.get(input.previous_output_index as usize).map(|o| o.script_pubkey.clone())
+		}
+		(&None, &None) => {
+			missing.push("witness_utxo".to_owned());
+			None
+		}
+	};
+	if let Some(spk) = spk {
+		let spk = if spk.is_p2sh() {
+			match input.redeem_script {
+				Some(ref rs) => rs.clone(),
+				None => {
+					missing.push("redeem_script".to_owned());
+					spk
+				}
+			}
+		} else {
+			spk
+		};
+		if spk.is_v0_p2wsh() && input.witness_script.is_none() {
+			missing.push("witness_script".to_owned());
+		}
+	}
+	if input.partial_sigs.is_empty() && input.tap_key_sig.is_none() {
+		missing.push("partial_sigs".to_owned());
+	}
+	missing
+}
+
+/// Computes the ids of the issued asset and, for new issuances, of the reissuance token.
+fn issuance_ids(input: &pset::Input) -> Option<(AssetId, Option<AssetId>)> {
+	let has_issuance = input.issuance_value_amount.is_some()
+		|| input.issuance_value_comm.is_some()
+		|| input.issuance_inflation_keys.is_some()
+		|| input.issuance_inflation_keys_comm.is_some();
+	if !has_issuance {
+		return None;
+	}
+
+	let entropy = input.issuance_asset_entropy.unwrap_or([0; 32]);
+	let is_reissuance = match input.issuance_blinding_nonce {
+		Some(nonce) => nonce != secp256k1_zkp::ZERO_TWEAK,
+		None => false,
+	};
+	if is_reissuance {
+		// For reissuances, the entropy field holds the entropy of the original issuance.
+		Some((AssetId::from_entropy(sha256::Midstate::from_byte_array(entropy)), None))
+	} else {
+		let prevout = elements::OutPoint::new(input.previous_txid, input.previous_output_index);
+		let ids = ::tx::IssuanceIdsInfo::new_issuance(
+			prevout,
+			ContractHash::from_byte_array(entropy),
+			input.issuance_value_comm.is_some(),
+		);
+		Some((ids.asset_id, Some(ids.token_id)))
+	}
 }
 
 impl ::GetInfo<PsetInputInfo> for pset::Input {
 	fn get_info(&self, network: Network) -> PsetInputInfo {
+		let finalized = self.final_script_sig.is_some() || self.final_script_witness.is_some();
+		let issuance_ids = issuance_ids(self);
 		PsetInputInfo {
 			non_witness_utxo: self.non_witness_utxo.as_ref().map(|u| u.get_info(network)),
 			witness_utxo: self.witness_utxo.as_ref().map(|u| u.get_info(network)),
 			partial_sigs: {
-				let mut partial_sigs = HashMap::new();
+				let mut partial_sigs = BTreeMap::new();
 				for (key, value) in self.partial_sigs.iter() {
-					partial_sigs.insert(key.to_bytes().into(), value.clone().into());
+					partial_sigs.insert(hex::encode(key.to_bytes()), value.clone().into());
 				}
 				partial_sigs
 			},
-			sighash_type: self.sighash_type.map(sighashtype_to_string),
+			sighash_type: self.sighash_type.map(|sht| {
+				// Non-standard types can't be named, so show them as a number.
+				sighashtype_to_string(sht).unwrap_or_else(|_| format!("{:#x}", sht.to_u32()))
+			}),
 			redeem_script: self.redeem_script.as_ref()
 				.map(|s| ::tx::OutputScript(s).get_info(network)),
 			witness_script: self.witness_script.as_ref()
 				.map(|s| ::tx::OutputScript(s).get_info(network)),
 			hd_keypaths: {
-				let mut hd_keypaths = HashMap::new();
+				let mut hd_keypaths = BTreeMap::new();
 				for (key, value) in self.bip32_derivation.iter() {
-					hd_keypaths.insert(key.to_bytes().into(),
+					hd_keypaths.insert(hex::encode(key.to_bytes()),
 						HDPathInfo {
 							master_fingerprint: value.0,
 							path: value.1.clone(),
@@ -218,40 +346,40 @@ impl ::GetInfo<PsetInputInfo> for pset::Input {
 			final_script_witness: self.final_script_witness.as_ref()
 				.map(|w| w.iter().map(|p| p.clone().into()).collect()),
 		    ripemd160_preimages: {
-				let mut ripemd160_map = HashMap::new();
+				let mut ripemd160_map = BTreeMap::new();
 				for (k, v) in &self.ripemd160_preimages{
 					ripemd160_map.insert(
-						HexBytes::from(k.to_byte_array().to_vec()),
+						hex::encode(k.to_byte_array().to_vec()),
 						HexBytes::from(v.to_vec())
 					);
 				}
 				ripemd160_map
 			},
 		    sha256_preimages: {
-				let mut sha256_map = HashMap::new();
+				let mut sha256_map = BTreeMap::new();
 				for (k, v) in &self.sha256_preimages{
 					sha256_map.insert(
-						HexBytes::from(k.to_byte_array().to_vec()),
+						hex::encode(k.to_byte_array().to_vec()),
 						HexBytes::from(v.to_vec())
 					);
 				}
 				sha256_map
 			},
 		    hash160_preimages: {
-				let mut hash160_map = HashMap::new();
+				let mut hash160_map = BTreeMap::new();
 				for (k, v) in &self.hash160_preimages{
 					hash160_map.insert(
-						HexBytes::from(k.to_byte_array().to_vec()),
+						hex::encode(k.to_byte_array().to_vec()),
 						HexBytes::from(v.to_vec())
 					);
 				}
 				hash160_map
 			},
 		    hash256_preimages: {
-				let mut hash256_map = HashMap::new();
+				let mut hash256_map = BTreeMap::new();
 				for (k, v) in &self.hash256_preimages{
 					hash256_map.insert(
-						HexBytes::from(k.to_byte_array().to_vec()),
+						hex::encode(k.to_byte_array().to_vec()),
 						HexBytes::from(v.to_vec())
 					);
 				}
@@ -259,7 +387,7 @@ impl ::GetInfo<PsetInputInfo> for pset::Input {
 			},
 		    previous_txid: HexBytes::from(self.previous_txid.to_byte_array().to_vec()),
 		    previous_output_index: self.previous_output_index,
-		    sequence: self.sequence.unwrap_or(elements::Sequence::MAX),
+		    sequence: self.sequence,
 		    required_time_locktime: self.required_time_locktime,
 		    required_height_locktime: self.required_height_locktime,
 		    issuance_value_amount:
@@ -287,26 +415,56 @@ impl ::GetInfo<PsetInputInfo> for pset::Input {
 				self.issuance_blinding_nonce.map(|x| HexBytes::from(encode::serialize(&x))),
 		    issuance_asset_entropy:
 				self.issuance_asset_entropy.map(|x| HexBytes::from(encode::serialize(&x))),
+			issuance_asset_id: issuance_ids.map(|(asset, _)| asset),
+			issuance_token_id: issuance_ids.and_then(|(_, token)| token),
+			tap_key_sig: self.tap_key_sig.map(|sig| sig.to_vec().into()),
+			tap_script_sigs: {
+				let mut tap_script_sigs = BTreeMap::new();
+				for (&(ref key, ref leaf), sig) in &self.tap_script_sigs {
+					let mut k = key.serialize().to_vec();
+					k.extend_from_slice(&leaf.to_byte_array());
+					tap_script_sigs.insert(hex::encode(k), sig.to_vec().into());
+				}
+				tap_script_sigs
+			},
+			tap_scripts: {
+				let mut tap_scripts = BTreeMap::new();
+				for (cb, &(ref script, ver)) in &self.tap_scripts {
+					tap_scripts.insert(hex::encode(cb.serialize()), TapScriptInfo {
+						script: script.to_bytes().into(),
+						leaf_version: ver.as_u8(),
+					});
+				}
+				tap_scripts
+			},
+			tap_internal_key: self.tap_internal_key.map(|k| k.serialize().to_vec().into()),
+			tap_merkle_root: self.tap_merkle_root.map(|h| h.to_byte_array().to_vec().into()),
 			proprietary: {
-				let mut proprietary = HashMap::new();
+				let mut proprietary = BTreeMap::new();
 				for (k, v) in &self.proprietary {
 					proprietary.insert(
-						HexBytes::from(encode::serialize(&k.to_key())),
+						hex::encode(encode::serialize(&k.to_key())),
 						HexBytes::from(v.to_vec()),
 					);
 				}
 				proprietary
 			},
 			unknown: {
-				let mut unknown = HashMap::new();
+				let mut unknown = BTreeMap::new();
 				for (k, v) in &self.unknown {
 					unknown.insert(
-						HexBytes::from(encode::serialize(k)),
+						hex::encode(encode::serialize(k)),
 						HexBytes::from(v.to_vec()),
 					);
 				}
 				unknown
 			},
+			finalized: finalized,
+			missing: if finalized {
+				Vec::new()
+			} else {
+				missing_input_fields(self)
+			},
 		}
 	}
 }
@@ -317,38 +475,65 @@ pub struct PsetOutputInfo {
 	pub redeem_script: Option<::tx::OutputScriptInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub witness_script: Option<::tx::OutputScriptInfo>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-	pub hd_keypaths: HashMap<::HexBytes, HDPathInfo>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub hd_keypaths: BTreeMap<String, HDPathInfo>,
 	pub amount: Option<::confidential::ConfidentialValueInfo>,
 	pub script_pubkey: ::tx::OutputScriptInfo,
 	pub asset: Option<::confidential::ConfidentialAssetInfo>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub amount_comm: Option<::confidential::ConfidentialValueInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub asset_comm: Option<::confidential::ConfidentialAssetInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub value_rangeproof: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub asset_surjection_proof: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blind_value_proof: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blind_asset_proof: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub blinding_key: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub ecdh_pubkey: Option<::HexBytes>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub blinder_index: Option<u32>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub proprietary: HashMap<::HexBytes, ::HexBytes>,
-	#[serde(skip_serializing_if = "HashMap::is_empty")]
-    pub unknown: HashMap<::HexBytes, ::HexBytes>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pegout_data: Option<::tx::PegoutDataInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tap_internal_key: Option<::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub proprietary: BTreeMap<String, ::HexBytes>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub unknown: BTreeMap<String, ::HexBytes>,
 }
 
 impl ::GetInfo<PsetOutputInfo> for pset::Output {
 	fn get_info(&self, network: Network) -> PsetOutputInfo {
+		// Pegouts have an explicit value and asset, so we only need those to detect them.
+		let pegout_data = match (self.amount, self.asset) {
+			(Some(value), Some(asset)) => {
+				let txout = elements::TxOut {
+					asset: confidential::Asset::Explicit(asset),
+					value: confidential::Value::Explicit(value),
+					nonce: confidential::Nonce::Null,
+					script_pubkey: self.script_pubkey.clone(),
+					witness: Default::default(),
+				};
+				txout.pegout_data().map(|p| p.get_info(network))
+			}
+			_ => None,
+		};
+
 		PsetOutputInfo {
 			redeem_script: self.redeem_script.as_ref()
 				.map(|s| ::tx::OutputScript(s).get_info(network)),
 			witness_script: self.witness_script.as_ref()
 				.map(|s| ::tx::OutputScript(s).get_info(network)),
 			hd_keypaths: {
-				let mut hd_keypaths = HashMap::new();
+				let mut hd_keypaths = BTreeMap::new();
 				for (key, value) in self.bip32_derivation.iter() {
-					hd_keypaths.insert(key.to_bytes().into(),
+					hd_keypaths.insert(hex::encode(key.to_bytes()),
 						HDPathInfo {
 							master_fingerprint: value.0,
 							path: value.1.clone(),
@@ -360,30 +545,38 @@ impl ::GetInfo<PsetOutputInfo> for pset::Output {
 		    amount: self.amount.map(|x| confidential::Value::Explicit(x).get_info(network)),
 		    script_pubkey: ::tx::OutputScript(&self.script_pubkey).get_info(network),
 		    asset: self.asset.map(|x| confidential::Asset::Explicit(x).get_info(network)),
+			amount_comm: self.amount_comm.map(|x| confidential::Value::Confidential(x).get_info(network)),
+			asset_comm: self.asset_comm.map(|x| confidential::Asset::Confidential(x).get_info(network)),
 		    value_rangeproof:
 				self.value_rangeproof.as_ref().map(|v| HexBytes::from(v.as_ref().serialize().clone())),
 		    asset_surjection_proof:
 				self.asset_surjection_proof.as_ref().map(|v| HexBytes::from(v.as_ref().serialize().clone())),
+			blind_value_proof:
+				self.blind_value_proof.as_ref().map(|v| HexBytes::from(v.as_ref().serialize().clone())),
+			blind_asset_proof:
+				self.blind_asset_proof.as_ref().map(|v| HexBytes::from(v.as_ref().serialize().clone())),
 		    blinding_key:
 				self.blinding_key.map(|x| HexBytes::from(x.to_bytes())),
 		    ecdh_pubkey:
 				self.ecdh_pubkey.map(|x| HexBytes::from(x.to_bytes())),
 		    blinder_index: self.blinder_index,
+		    pegout_data: pegout_data,
+			tap_internal_key: self.tap_internal_key.map(|k| k.serialize().to_vec().into()),
 			proprietary: {
-				let mut proprietary = HashMap::new();
+				let mut proprietary = BTreeMap::new();
 				for (k, v) in &self.proprietary {
 					proprietary.insert(
-						HexBytes::from(encode::serialize(&k.to_key())),
+						hex::encode(encode::serialize(&k.to_key())),
 						HexBytes::from(v.to_vec()),
 					);
 				}
 				proprietary
 			},
 			unknown: {
-				let mut unknown = HashMap::new();
+				let mut unknown = BTreeMap::new();
 				for (k, v) in &self.unknown {
 					unknown.insert(
-						HexBytes::from(encode::serialize(k)),
+						hex::encode(encode::serialize(k)),
 						HexBytes::from(v.to_vec()),
 					);
 				}
@@ -393,19 +586,448 @@ impl ::GetInfo<PsetOutputInfo> for pset::Output {
 	}
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
 pub struct PsetInfo {
 	pub global: PsetGlobalInfo,
 	pub inputs: Vec<PsetInputInfo>,
 	pub outputs: Vec<PsetOutputInfo>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee_rate_sat_vb: Option<f64>,
+}
+
+/// Compute the fee paid by the PSET.
+///
+/// This is only possible when a fee output is present and the amounts of all inputs and
+/// outputs are explicit. Amounts of other assets than the fee asset are ignored.
+pub fn pset_fee(
+	pset: &pset::PartiallySignedTransaction,
+	policy_asset: Option<AssetId>,
+) -> Option<u64> {
+	let is_fee = |o: &pset::Output| ::tx::is_fee_output(&o.to_txout(), policy_asset);
+	let fee_asset = pset.outputs().iter().find(|o| is_fee(o))?.asset?;
+
+	let mut total_in = 0u64;
+	for input in pset.inputs() {
+		let utxo = match input.witness_utxo {
+			Some(ref utxo) => utxo,
+			None => input.non_witness_utxo.as_ref()?
+				.output.get(input.previous_output_index as usize)?,
+		};
+		match (utxo.asset, utxo.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				if asset == fee_asset {
+					total_in = total_in.checked_add(value)?;
+				}
+			}
+			_ => return None,
+		}
+	}
+
+	let mut total_out = 0u64;
+	for output in pset.outputs() {
+		if output.asset? == fee_asset && !is_fee(output) {
+			total_out = total_out.checked_add(output.amount?)?;
+		}
+	}
+	total_in.checked_sub(total_out)
 }
 
 impl ::GetInfo<PsetInfo> for pset::PartiallySignedTransaction {
 	fn get_info(&self, network: Network) -> PsetInfo {
+		let fee = pset_fee(self, network.policy_asset());
 		PsetInfo {
 			global: self.global.get_info(network),
 			inputs: self.inputs().iter().map(|i| i.get_info(network)).collect(),
 			outputs: self.outputs().iter().map(|o| o.get_info(network)).collect(),
+			fee: fee,
+			fee_rate_sat_vb: fee.and_then(|fee| {
+				// Signatures are not yet present, so this is only an estimate.
+				let vsize = ::tx::vsize(self.extract_tx().ok()?.weight());
+				Some(fee as f64 / vsize as f64)
+			}),
 		}
 	}
 }
+
+fn hex_key(field: &'static str, key: &str) -> Result<Vec<u8>, Error> {
+	hex::decode(key).map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_public_key(field: &'static str, bytes: &[u8]) -> Result<::bitcoin::PublicKey, Error> {
+	::bitcoin::PublicKey::from_slice(bytes).map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_rangeproof(
+	field: &'static str,
+	proof: &HexBytes,
+) -> Result<Box<secp256k1_zkp::RangeProof>, Error> {
+	secp256k1_zkp::RangeProof::from_slice(proof.bytes())
+		.map(Box::new)
+		.map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_surjection_proof(
+	field: &'static str,
+	proof: &HexBytes,
+) -> Result<Box<secp256k1_zkp::SurjectionProof>, Error> {
+	secp256k1_zkp::SurjectionProof::from_slice(proof.bytes())
+		.map(Box::new)
+		.map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_hd_keypaths(
+	field: &'static str,
+	keypaths: BTreeMap<String, HDPathInfo>,
+) -> Result<BTreeMap<::bitcoin::PublicKey, bip32::KeySource>, Error> {
+	let mut ret = BTreeMap::new();
+	for (key, path) in keypaths {
+		let key = create_public_key(field, &hex_key(field, &key)?)?;
+		ret.insert(key, (path.master_fingerprint, path.path));
+	}
+	Ok(ret)
+}
+
+fn create_proprietary(
+	proprietary: BTreeMap<String, HexBytes>,
+) -> Result<BTreeMap<pset::raw::ProprietaryKey, Vec<u8>>, Error> {
+	let mut ret = BTreeMap::new();
+	for (key, value) in proprietary {
+		let key: pset::raw::Key = encode::deserialize(&hex_key("proprietary", &key)?)
+			.map_err(|e| Error::InvalidField("proprietary", e.to_string()))?;
+		let key = pset::raw::ProprietaryKey::from_key(key)
+			.map_err(|e| Error::InvalidField("proprietary", e.to_string()))?;
+		ret.insert(key, value.0);
+	}
+	Ok(ret)
+}
+
+fn create_unknown(
+	unknown: BTreeMap<String, HexBytes>,
+) -> Result<BTreeMap<pset::raw::Key, Vec<u8>>, Error> {
+	let mut ret = BTreeMap::new();
+	for (key, value) in unknown {
+		let key = encode::deserialize(&hex_key("unknown", &key)?)
+			.map_err(|e| Error::InvalidField("unknown", e.to_string()))?;
+		ret.insert(key, value.0);
+	}
+	Ok(ret)
+}
+
+fn create_xonly_key(
+	field: &'static str,
+	bytes: &[u8],
+) -> Result<secp256k1_zkp::XOnlyPublicKey, Error> {
+	secp256k1_zkp::XOnlyPublicKey::from_slice(bytes)
+		.map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_schnorr_sig(field: &'static str, bytes: &[u8]) -> Result<SchnorrSig, Error> {
+	SchnorrSig::from_slice(bytes).map_err(|e| Error::InvalidField(field, e.to_string()))
+}
+
+fn create_preimages<H: Hash + Ord>(
+	field: &'static str,
+	preimages: BTreeMap<String, HexBytes>,
+) -> Result<BTreeMap<H, Vec<u8>>, Error> {
+	let mut ret = BTreeMap::new();
+	for (hash, preimage) in preimages {
+		let hash = H::from_slice(&hex_key(field, &hash)?)
+			.map_err(|e| Error::InvalidField(field, e.to_string()))?;
+		ret.insert(hash, preimage.0);
+	}
+	Ok(ret)
+}
+
+/// Split a confidential value into its explicit value and its commitment.
+fn split_value(
+	value: Option<::confidential::ConfidentialValueInfo>,
+) -> Result<(Option<u64>, Option<secp256k1_zkp::PedersenCommitment>), Error> {
+	match value {
+		Some(info) => match ::tx::create_confidential_value(info)? {
+			confidential::Value::Null => Ok((None, None)),
+			confidential::Value::Explicit(v) => Ok((Some(v), None)),
+			confidential::Value::Confidential(c) => Ok((None, Some(c))),
+		},
+		None => Ok((None, None)),
+	}
+}
+
+/// Split a confidential asset into its explicit asset id and its commitment.
+fn split_asset(
+	asset: Option<::confidential::ConfidentialAssetInfo>,
+) -> Result<(Option<AssetId>, Option<secp256k1_zkp::Generator>), Error> {
+	match asset {
+		Some(info) => match ::tx::create_confidential_asset(info)? {
+			confidential::Asset::Null => Ok((None, None)),
+			confidential::Asset::Explicit(a) => Ok((Some(a), None)),
+			confidential::Asset::Confidential(g) => Ok((None, Some(g))),
+		},
+		None => Ok((None, None)),
+	}
+}
+
+fn create_pset_global(info: PsetGlobalInfo, pset: &mut pset::PartiallySignedTransaction) -> Result<(), Error> {
+	if info.version != 2 {
+		return Err(Error::InvalidField("version", "only PSET version 2 is supported".into()));
+	}
+
+	if info.unsigned_tx.is_some() {
+		warn!("Field \"unsigned_tx\" is ignored.");
+	}
+
+	let global = &mut pset.global;
+	global.tx_data.version = info.tx_version;
+	global.tx_data.fallback_locktime = info.fallback_locktime;
+	if info.tx_modifiable != 0 {
+		global.tx_data.tx_modifiable = Some(info.tx_modifiable);
+	}
+	if info.elements_tx_modifiable_flag != 0 {
+		global.elements_tx_modifiable_flag = Some(info.elements_tx_modifiable_flag);
+	}
+	for (xpub, source) in info.xpub {
+		let xpub = xpub.parse::<bip32::ExtendedPubKey>()
+			.map_err(|e| Error::InvalidField("xpub", e.to_string()))?;
+		// The key source is formatted as "(fingerprint,path)".
+		let source = source.trim_start_matches('(').trim_end_matches(')');
+		let mut parts = source.splitn(2, ',');
+		let fingerprint = parts.next().unwrap().parse::<bip32::Fingerprint>()
+			.map_err(|e| Error::InvalidField("xpub", e.to_string()))?;
+		let path = parts.next()
+			.ok_or_else(|| Error::InvalidField("xpub", "missing derivation path".into()))?
+			.parse::<bip32::DerivationPath>()
+			.map_err(|e| Error::InvalidField("xpub", e.to_string()))?;
+		global.xpub.insert(xpub, (fingerprint, path));
+	}
+	for scalar in info.scalars {
+		global.scalars.push(
+			secp256k1_zkp::Tweak::from_slice(scalar.bytes())
+				.map_err(|e| Error::InvalidField("scalars", e.to_string()))?,
+		);
+	}
+	global.proprietary = create_proprietary(info.proprietary)?;
+	global.unknown = create_unknown(info.unknown)?;
+	Ok(())
+}
+
+fn create_pset_input(info: PsetInputInfo) -> Result<pset::Input, Error> {
+	let txid = elements::Txid::from_slice(info.previous_txid.bytes())
+		.map_err(|e| Error::InvalidField("previous_txid", e.to_string()))?;
+	let mut input = pset::Input::from_prevout(elements::OutPoint::new(txid, info.previous_output_index));
+
+	input.non_witness_utxo = match info.non_witness_utxo {
+		Some(tx) => Some(::tx::create_transaction(tx, None, &mut None)?),
+		None => None,
+	};
+	input.witness_utxo = match info.witness_utxo {
+		Some(out) => Some(::tx::create_output(out, None, &mut None)?),
+		None => None,
+	};
+	for (key, sig) in info.partial_sigs {
+		let key = create_public_key("partial_sigs", &hex_key("partial_sigs", &key)?)?;
+		input.partial_sigs.insert(key, sig.0);
+	}
+	input.sighash_type = match info.sighash_type {
+		// Non-standard types are shown as a number.
+		Some(ref sht) if sht.starts_with("0x") => Some(PsbtSighashType::from_u32(
+			u32::from_str_radix(&sht[2..], 16)
+				.map_err(|e| Error::InvalidField("sighash_type", e.to_string()))?,
+		)),
+		Some(ref sht) => Some(PsbtSighashType::from_u32(sighashtype_from_string(sht)?.as_u32())),
+		None => None,
+	};
+	input.redeem_script = match info.redeem_script {
+		Some(s) => Some(::tx::create_script_pubkey(s, None, &mut None)?),
+		None => None,
+	};
+	input.witness_script = match info.witness_script {
+		Some(s) => Some(::tx::create_script_pubkey(s, None, &mut None)?),
+		None => None,
+	};
+	input.bip32_derivation = create_hd_keypaths("hd_keypaths", info.hd_keypaths)?;
+	input.final_script_sig = match info.final_script_sig {
+		Some(s) => Some(::tx::create_script_sig(s)?),
+		None => None,
+	};
+	input.final_script_witness =
+		info.final_script_witness.map(|w| w.into_iter().map(|p| p.0).collect());
+	input.ripemd160_preimages = create_preimages("ripemd160_preimages", info.ripemd160_preimages)?;
+	input.sha256_preimages = create_preimages("sha256_preimages", info.sha256_preimages)?;
+	input.hash160_preimages = create_preimages("hash160_preimages", info.hash160_preimages)?;
+	input.hash256_preimages = create_preimages("hash256_preimages", info.hash256_preimages)?;
+	input.sequence = info.sequence;
+	input.required_time_locktime = info.required_time_locktime;
+	input.required_height_locktime = info.required_height_locktime;
+
+	let (amount, amount_comm) = split_value(info.issuance_value_amount)?;
+	let (_, comm) = split_value(info.issuance_value_comm)?;
+	input.issuance_value_amount = amount;
+	input.issuance_value_comm = comm.or(amount_comm);
+	let (keys, keys_comm) = split_value(info.issuance_inflation_keys)?;
+	let (_, comm) = split_value(info.issuance_inflation_keys_comm)?;
+	input.issuance_inflation_keys = keys;
+	input.issuance_inflation_keys_comm = comm.or(keys_comm);
+	input.issuance_value_rangeproof = match info.issuance_value_rangeproof {
+		Some(ref p) => Some(create_rangeproof("issuance_value_rangeproof", p)?),
+		None => None,
+	};
+	input.issuance_keys_rangeproof = match info.issuance_keys_rangeproof {
+		Some(ref p) => Some(create_rangeproof("issuance_keys_rangeproof", p)?),
+		None => None,
+	};
+	input.issuance_blinding_nonce = match info.issuance_blinding_nonce {
+		Some(ref n) => Some(
+			secp256k1_zkp::Tweak::from_inner(::tx::fixed_bytes::<32>("issuance_blinding_nonce", n.bytes())?)
+				.map_err(|e| Error::InvalidField("issuance_blinding_nonce", e.to_string()))?,
+		),
+		None => None,
+	};
+	input.issuance_asset_entropy = match info.issuance_asset_entropy {
+		Some(ref e) => Some(::tx::fixed_bytes::<32>("issuance_asset_entropy", e.bytes())?),
+		None => None,
+	};
+
+	input.pegin_tx = match info.pegin_tx {
+		Some(ref tx) => Some(
+			::bitcoin::consensus::deserialize(tx.bytes())
+				.map_err(|e| Error::InvalidField("pegin_tx", e.to_string()))?,
+		),
+		None => None,
+	};
+	input.pegin_txout_proof = info.pegin_txout_proof.map(|p| p.0);
+	input.pegin_genesis_hash = match info.pegin_genesis_hash {
+		Some(ref h) => Some(
+			::bitcoin::BlockHash::from_slice(h.bytes())
+				.map_err(|e| Error::InvalidField("pegin_genesis_hash", e.to_string()))?,
+		),
+		None => None,
+	};
+	input.pegin_claim_script = match info.pegin_claim_script {
+		Some(s) => Some(::tx::create_script_sig(s)?),
+		None => None,
+	};
+	input.pegin_value = info.pegin_value;
+	input.pegin_witness = info.pegin_witness.map(|w| w.into_iter().map(|p| p.0).collect());
+
+	input.tap_key_sig = match info.tap_key_sig {
+		Some(ref sig) => Some(create_schnorr_sig("tap_key_sig", sig.bytes())?),
+		None => None,
+	};
+	for (key, sig) in info.tap_script_sigs {
+		let key = hex_key("tap_script_sigs", &key)?;
+		if key.len() != 64 {
+			return Err(Error::InvalidField(
+				"tap_script_sigs",
+				"key must be an x-only public key followed by a leaf hash".into(),
+			));
+		}
+		let pk = create_xonly_key("tap_script_sigs", &key[..32])?;
+		let leaf = TapLeafHash::from_slice(&key[32..])
+			.map_err(|e| Error::InvalidField("tap_script_sigs", e.to_string()))?;
+		input.tap_script_sigs.insert((pk, leaf), create_schnorr_sig("tap_script_sigs", sig.bytes())?);
+	}
+	for (cb, leaf) in info.tap_scripts {
+		let cb = ControlBlock::from_slice(&hex_key("tap_scripts", &cb)?)
+			.map_err(|e| Error::InvalidField("tap_scripts", e.to_string()))?;
+		let ver = LeafVersion::from_u8(leaf.leaf_version)
+			.map_err(|e| Error::InvalidField("tap_scripts", e.to_string()))?;
+		input.tap_scripts.insert(cb, (leaf.script.0.into(), ver));
+	}
+	input.tap_internal_key = match info.tap_internal_key {
+		Some(ref k) => Some(create_xonly_key("tap_internal_key", k.bytes())?),
+		None => None,
+	};
+	input.tap_merkle_root = match info.tap_merkle_root {
+		Some(ref h) => Some(
+			TapNodeHash::from_slice(h.bytes())
+				.map_err(|e| Error::InvalidField("tap_merkle_root", e.to_string()))?,
+		),
+		None => None,
+	};
+
+	input.proprietary = create_proprietary(info.proprietary)?;
+	input.unknown = create_unknown(info.unknown)?;
+	Ok(input)
+}
+
+fn create_pset_output(info: PsetOutputInfo) -> Result<pset::Output, Error> {
+	let mut output = pset::Output::default();
+
+	output.redeem_script = match info.redeem_script {
+		Some(s) => Some(::tx::create_script_pubkey(s, None, &mut None)?),
+		None => None,
+	};
+	output.witness_script = match info.witness_script {
+		Some(s) => Some(::tx::create_script_pubkey(s, None, &mut None)?),
+		None => None,
+	};
+	output.bip32_derivation = create_hd_keypaths("hd_keypaths", info.hd_keypaths)?;
+	let (amount, amount_comm) = split_value(info.amount)?;
+	let (_, comm) = split_value(info.amount_comm)?;
+	output.amount = amount;
+	output.amount_comm = comm.or(amount_comm);
+	output.script_pubkey = ::tx::create_script_pubkey(info.script_pubkey, None, &mut None)?;
+	let (asset, asset_comm) = split_asset(info.asset)?;
+	let (_, comm) = split_asset(info.asset_comm)?;
+	output.asset = asset;
+	output.asset_comm = comm.or(asset_comm);
+	output.value_rangeproof = match info.value_rangeproof {
+		Some(ref p) => Some(create_rangeproof("value_rangeproof", p)?),
+		None => None,
+	};
+	output.asset_surjection_proof = match info.asset_surjection_proof {
+		Some(ref p) => Some(create_surjection_proof("asset_surjection_proof", p)?),
+		None => None,
+	};
+	output.blind_value_proof = match info.blind_value_proof {
+		Some(ref p) => Some(create_rangeproof("blind_value_proof", p)?),
+		None => None,
+	};
+	output.blind_asset_proof = match info.blind_asset_proof {
+		Some(ref p) => Some(create_surjection_proof("blind_asset_proof", p)?),
+		None => None,
+	};
+	output.blinding_key = match info.blinding_key {
+		Some(ref k) => Some(create_public_key("blinding_key", k.bytes())?),
+		None => None,
+	};
+	output.ecdh_pubkey = match info.ecdh_pubkey {
+		Some(ref k) => Some(create_public_key("ecdh_pubkey", k.bytes())?),
+		None => None,
+	};
+	output.blinder_index = info.blinder_index;
+	if info.pegout_data.is_some() {
+		warn!("Field \"pegout_data\" of output is ignored.");
+	}
+	output.tap_internal_key = match info.tap_internal_key {
+		Some(ref k) => Some(create_xonly_key("tap_internal_key", k.bytes())?),
+		None => None,
+	};
+
+	output.proprietary = create_proprietary(info.proprietary)?;
+	output.unknown = create_unknown(info.unknown)?;
+	Ok(output)
+}
+
+/// Create a PSET from its JSON info, as output by decoding it.
+///
+/// Fields that are computed when decoding, like the fee and issuance ids, are ignored.
+pub fn create_pset(info: PsetInfo) -> Result<pset::PartiallySignedTransaction, Error> {
+	if info.global.num_inputs as usize != info.inputs.len() {
+		warn!("Field \"num_inputs\" does not match the number of inputs and is ignored.");
+	}
+	if info.global.num_outputs as usize != info.outputs.len() {
+		warn!("Field \"num_outputs\" does not match the number of outputs and is ignored.");
+	}
+
+	let mut pset = pset::PartiallySignedTransaction::new_v2();
+	create_pset_global(info.global, &mut pset)?;
+	for (i, input) in info.inputs.into_iter().enumerate() {
+		pset.add_input(create_pset_input(input).map_err(|e| Error::Input(i, Box::new(e)))?);
+	}
+	for (i, output) in info.outputs.into_iter().enumerate() {
+		pset.add_output(create_pset_output(output).map_err(|e| Error::Output(i, Box::new(e)))?);
+	}
+	Ok(pset)
+}
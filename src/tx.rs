@@ -1,13 +1,19 @@
+use std::collections::BTreeMap;
+
 use elements::encode::serialize;
+use elements::hashes::{sha256, Hash};
+use elements::secp256k1_zkp::{self, RangeProof, SurjectionProof};
 use elements::{
-	bitcoin, confidential, AssetIssuance, PeginData, PegoutData, Transaction, TxIn, TxInWitness,
-	TxOut, TxOutWitness, Address, Script
+	bitcoin, confidential, AssetId, AssetIssuance, ContractHash, OutPoint, PeginData, PegoutData,
+	Transaction, TxIn, TxInWitness, TxOut, TxOutWitness, Address, Script
 };
 use serde::{Deserialize, Serialize};
 
-use ::{GetInfo, Network, HexBytes};
+use ::{CustomNetwork, Error, GetInfo, Network, HexBytes};
 
-use confidential::{ConfidentialAssetInfo, ConfidentialNonceInfo, ConfidentialValueInfo};
+use confidential::{
+	ConfidentialAssetInfo, ConfidentialNonceInfo, ConfidentialType, ConfidentialValueInfo,
+};
 
 const BTCNET: elements::bitcoin::Network = elements::bitcoin::Network::Testnet;
 
@@ -23,29 +29,35 @@ pub struct OutputScriptInfo {
 
 pub struct OutputScript<'a>(pub &'a Script);
 
+/// Classify an output script like Bitcoin Core does, with empty scripts being fee outputs.
+pub fn output_script_type(script: &Script) -> &'static str {
+	if script.is_empty() {
+		"fee"
+	} else if script.is_p2pk() {
+		"p2pk"
+	} else if script.is_p2pkh() {
+		"p2pkh"
+	} else if script.is_op_return() {
+		"opreturn"
+	} else if script.is_p2sh() {
+		"p2sh"
+	} else if script.is_v0_p2wpkh() {
+		"p2wpkh"
+	} else if script.is_v0_p2wsh() {
+		"p2wsh"
+	} else if script.is_v1_p2tr() {
+		"p2tr"
+	} else {
+		"unknown"
+	}
+}
+
 impl<'a> ::GetInfo<OutputScriptInfo> for OutputScript<'a> {
 	fn get_info(&self, network: Network) -> OutputScriptInfo {
 		OutputScriptInfo {
 			hex: Some(self.0.to_bytes().into()),
 			asm: Some(self.0.asm()),
-			type_: Some(
-				if self.0.is_p2pk() {
-					"p2pk"
-				} else if self.0.is_p2pkh() {
-					"p2pkh"
-				} else if self.0.is_op_return() {
-					"opreturn"
-				} else if self.0.is_p2sh() {
-					"p2sh"
-				} else if self.0.is_v0_p2wpkh() {
-					"p2wpkh"
-				} else if self.0.is_v0_p2wsh() {
-					"p2wsh"
-				} else {
-					"unknown"
-				}
-				.to_owned(),
-			),
+			type_: Some(output_script_type(self.0).to_owned()),
 			unblinded_address: Address::from_script(&self.0, None, network.address_params()),
 		}
 	}
@@ -73,6 +85,14 @@ pub struct AssetIssuanceInfo {
 	pub asset_entropy: Option<HexBytes>,
 	pub amount: Option<ConfidentialValueInfo>,
 	pub inflation_keys: Option<ConfidentialValueInfo>,
+	/// Only known when decoding an input, because it needs the prevout.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub is_reissuance: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub asset_id: Option<AssetId>,
+	/// Only set for new issuances.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub token_id: Option<AssetId>,
 }
 
 impl GetInfo<AssetIssuanceInfo> for AssetIssuance {
@@ -82,28 +102,49 @@ impl GetInfo<AssetIssuanceInfo> for AssetIssuance {
 			asset_entropy: Some(self.asset_entropy[..].into()),
 			amount: Some(self.amount.get_info(network)),
 			inflation_keys: Some(self.inflation_keys.get_info(network)),
+			is_reissuance: None,
+			asset_id: None,
+			token_id: None,
 		}
 	}
 }
 
+/// Get the info of the issuance of the input, including the issued asset ids.
+fn input_issuance_info(txin: &TxIn, network: Network) -> AssetIssuanceInfo {
+	let mut info = txin.asset_issuance.get_info(network);
+	// Reissuances have a non-zero blinding nonce.
+	let is_reissuance = txin.asset_issuance.asset_blinding_nonce != secp256k1_zkp::ZERO_TWEAK;
+	let (asset_id, token_id) = txin.issuance_ids();
+	info.is_reissuance = Some(is_reissuance);
+	info.asset_id = Some(asset_id);
+	info.token_id = if is_reissuance {
+		None
+	} else {
+		Some(token_id)
+	};
+	info
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct PeginDataInfo {
-	pub outpoint: String,
-	pub value: u64,
+	/// Taken from the input when omitted on creation.
+	pub outpoint: Option<String>,
+	/// Taken from the mainchain transaction when omitted on creation.
+	pub value: Option<u64>,
 	pub asset: ConfidentialAssetInfo,
 	pub genesis_hash: bitcoin::BlockHash,
 	pub claim_script: HexBytes,
 	pub mainchain_tx_hex: HexBytes,
 	pub mainchain_tx: Option<hal::tx::TransactionInfo>,
 	pub merkle_proof: HexBytes,
-	pub referenced_block: bitcoin::BlockHash,
+	pub referenced_block: Option<bitcoin::BlockHash>,
 }
 
 impl<'tx> GetInfo<PeginDataInfo> for PeginData<'tx> {
 	fn get_info(&self, network: Network) -> PeginDataInfo {
 		PeginDataInfo {
-			outpoint: self.outpoint.to_string(),
-			value: self.value,
+			outpoint: Some(self.outpoint.to_string()),
+			value: Some(self.value),
 			asset: confidential::Asset::Explicit(self.asset).get_info(network),
 			genesis_hash: self.genesis_hash,
 			claim_script: self.claim_script.into(),
@@ -113,7 +154,7 @@ impl<'tx> GetInfo<PeginDataInfo> for PeginData<'tx> {
 				Err(_) => None,
 			},
 			merkle_proof: self.merkle_proof.into(),
-			referenced_block: self.referenced_block,
+			referenced_block: Some(self.referenced_block),
 		}
 	}
 }
@@ -126,6 +167,34 @@ pub struct InputWitnessInfo {
 	pub script_witness: Option<Vec<HexBytes>>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub pegin_witness: Option<Vec<HexBytes>>,
+	/// The pegin witness elements with their meaning, see [label_pegin_witness].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pegin_witness_labeled: Option<Vec<WitnessElementInfo>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct WitnessElementInfo {
+	pub label: String,
+	pub hex: HexBytes,
+}
+
+/// The meaning of the elements of a pegin witness, in stack order.
+const PEGIN_WITNESS_LABELS: [&'static str; 6] =
+	["value", "asset", "genesis_hash", "claim_script", "mainchain_tx", "merkle_proof"];
+
+/// Label each element of the pegin witness with its meaning.
+///
+/// Elements beyond the six of a valid pegin witness are labeled "unknown".
+pub fn label_pegin_witness(info: &mut InputWitnessInfo, witness: &TxInWitness) {
+	if witness.pegin_witness.is_empty() {
+		return;
+	}
+	info.pegin_witness_labeled = Some(witness.pegin_witness.iter().enumerate().map(|(i, w)| {
+		WitnessElementInfo {
+			label: PEGIN_WITNESS_LABELS.get(i).unwrap_or(&"unknown").to_string(),
+			hex: w.clone().into(),
+		}
+	}).collect());
 }
 
 impl GetInfo<InputWitnessInfo> for TxInWitness {
@@ -143,6 +212,7 @@ impl GetInfo<InputWitnessInfo> for TxInWitness {
 			} else {
 				None
 			},
+			pegin_witness_labeled: None,
 		}
 	}
 }
@@ -164,6 +234,13 @@ pub struct InputInfo {
 
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub pegin_data: Option<PeginDataInfo>,
+
+	/// The value of the spent output, only known when the output is provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub value: Option<ConfidentialValueInfo>,
+	/// The asset of the spent output, only known when the output is provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub asset: Option<ConfidentialAssetInfo>,
 }
 
 impl GetInfo<InputInfo> for TxIn {
@@ -179,7 +256,7 @@ impl GetInfo<InputInfo> for TxIn {
 			is_pegin: Some(self.is_pegin),
 			has_issuance: Some(self.has_issuance()),
 			asset_issuance: if self.has_issuance() {
-				Some(self.asset_issuance.get_info(network))
+				Some(input_issuance_info(self, network))
 			} else {
 				None
 			},
@@ -189,6 +266,8 @@ impl GetInfo<InputInfo> for TxIn {
 				None
 			},
 			pegin_data: self.pegin_data().map(|p| p.get_info(network)),
+			value: None,
+			asset: None,
 		}
 	}
 }
@@ -241,33 +320,148 @@ pub struct OutputInfo {
 
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub pegout_data: Option<PegoutDataInfo>,
+	/// The data pushed by an OP_RETURN output that is not a pegout.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub op_return_data: Option<Vec<HexBytes>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rangeproof_valid: Option<bool>,
+}
+
+/// Collect the data pushes of an OP_RETURN script.
+///
+/// Returns `None` if the script doesn't start with OP_RETURN.
+pub fn op_return_data(script: &Script) -> Option<Vec<HexBytes>> {
+	let mut instructions = script.instructions();
+	match instructions.next() {
+		Some(Ok(elements::script::Instruction::Op(elements::opcodes::all::OP_RETURN))) => {}
+		_ => return None,
+	}
+	Some(instructions.filter_map(|i| match i {
+		Ok(elements::script::Instruction::PushBytes(data)) => Some(data.to_vec().into()),
+		_ => None,
+	}).collect())
+}
+
+/// Whether the output is a fee output.
+///
+/// An output is fee if both the asset and the value are explicit, if the output script is
+/// empty and if the asset is the policy asset. OP_RETURN data outputs have a non-empty script
+/// and are never fee. If the policy asset is not known, any asset is accepted.
+pub fn is_fee_output(txout: &TxOut, policy_asset: Option<AssetId>) -> bool {
+	let asset = match txout.asset {
+		confidential::Asset::Explicit(a) => a,
+		_ => return false,
+	};
+	txout.value.explicit().is_some()
+		&& txout.script_pubkey.is_empty()
+		&& policy_asset.map(|p| p == asset).unwrap_or(true)
 }
 
 impl GetInfo<OutputInfo> for TxOut {
 	fn get_info(&self, network: Network) -> OutputInfo {
-		let is_fee = {
-			// An output is fee if both the asset and the value are explicit
-			// and if the output script is empty.
-			let exp_ass = match self.asset {
-				confidential::Asset::Explicit(_) => true,
-				_ => false,
-			};
-			let exp_val = match self.value {
-				confidential::Value::Explicit(_) => true,
-				_ => false,
-			};
-
-			exp_ass && exp_val && self.script_pubkey.len() == 0
+		let is_fee = is_fee_output(self, network.policy_asset());
+		let pegout_data = self.pegout_data().map(|p| p.get_info(network));
+		let mut script_pub_key = GetInfo::get_info(&OutputScript(&self.script_pubkey), network);
+		let op_return_data = if pegout_data.is_some() {
+			script_pub_key.type_ = Some("pegout".to_owned());
+			None
+		} else {
+			op_return_data(&self.script_pubkey)
 		};
 
 		OutputInfo {
-			script_pub_key: Some(GetInfo::get_info(&OutputScript(&self.script_pubkey), network)),
+			script_pub_key: Some(script_pub_key),
 			asset: Some(self.asset.get_info(network)),
 			value: Some(self.value.get_info(network)),
 			nonce: Some(self.nonce.get_info(network)),
 			witness: Some(self.witness.get_info(network)),
 			is_fee: Some(is_fee),
-			pegout_data: self.pegout_data().map(|p| p.get_info(network)),
+			pegout_data: pegout_data,
+			op_return_data: op_return_data,
+			rangeproof_valid: None,
+		}
+	}
+}
+
+/// Verify the rangeproof of the output against its value and asset commitments.
+///
+/// Returns `None` if the output has no rangeproof or no value commitment.
+/// Surjection proofs can't be verified here, because that requires the assets of the inputs.
+pub fn verify_rangeproof<C: secp256k1_zkp::Signing + secp256k1_zkp::Verification>(
+	secp: &secp256k1_zkp::Secp256k1<C>,
+	txout: &TxOut,
+) -> Option<bool> {
+	let rangeproof = txout.witness.rangeproof.as_ref()?;
+	let commitment = match txout.value {
+		confidential::Value::Confidential(c) => c,
+		_ => return None,
+	};
+	let generator = match asset_generator(secp, txout.asset) {
+		Some(g) => g,
+		None => return Some(false),
+	};
+	Some(rangeproof.verify(secp, commitment, txout.script_pubkey.as_bytes(), generator).is_ok())
+}
+
+/// Get the info of the value of the output, including the minimum value proven by its
+/// rangeproof if the value is confidential.
+pub fn output_value_info<C: secp256k1_zkp::Signing + secp256k1_zkp::Verification>(
+	secp: &secp256k1_zkp::Secp256k1<C>,
+	txout: &TxOut,
+	network: Network,
+) -> ConfidentialValueInfo {
+	let rangeproof = txout.witness.rangeproof.as_ref();
+	match (txout.value, rangeproof, asset_generator(secp, txout.asset)) {
+		(confidential::Value::Confidential(_), Some(rangeproof), Some(generator)) => {
+			::confidential::value_info_with_rangeproof(
+				&txout.value,
+				network,
+				secp,
+				rangeproof,
+				generator,
+				txout.script_pubkey.as_bytes(),
+			)
+		}
+		_ => txout.value.get_info(network),
+	}
+}
+
+/// Get the generator of the asset, or `None` for a null asset.
+fn asset_generator<C: secp256k1_zkp::Signing>(
+	secp: &secp256k1_zkp::Secp256k1<C>,
+	asset: confidential::Asset,
+) -> Option<secp256k1_zkp::Generator> {
+	match asset {
+		confidential::Asset::Confidential(g) => Some(g),
+		confidential::Asset::Explicit(a) => {
+			Some(secp256k1_zkp::Generator::new_unblinded(secp, a.into_tag()))
+		}
+		confidential::Asset::Null => None,
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockTimeType {
+	Height,
+	Time,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct LockTimeInfo {
+	#[serde(rename = "type")]
+	pub type_: LockTimeType,
+	pub value: u32,
+}
+
+impl GetInfo<LockTimeInfo> for elements::LockTime {
+	fn get_info(&self, _network: Network) -> LockTimeInfo {
+		LockTimeInfo {
+			type_: match self.is_block_height() {
+				true => LockTimeType::Height,
+				false => LockTimeType::Time,
+			},
+			value: self.to_consensus_u32(),
 		}
 	}
 }
@@ -280,25 +474,863 @@ pub struct TransactionInfo {
 	pub size: Option<usize>,
 	pub weight: Option<usize>,
 	pub vsize: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub witness_weight: Option<usize>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub non_witness_weight: Option<usize>,
 	pub version: Option<u32>,
 	pub locktime: Option<elements::LockTime>,
+	/// Whether the locktime is a block height or a time.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub locktime_info: Option<LockTimeInfo>,
 	pub inputs: Option<Vec<InputInfo>>,
 	pub outputs: Option<Vec<OutputInfo>>,
+	/// Only known when all spent outputs are provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fee: Option<u64>,
+	/// The total explicit input and output amounts per asset.
+	/// Only known when all spent outputs are provided.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub balances: Option<BTreeMap<AssetId, AssetBalanceInfo>>,
+	/// Whether any input or output is confidential and thus excluded from the balances.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub balances_exclude_confidential: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+pub struct AssetBalanceInfo {
+	#[serde(rename = "in")]
+	pub in_: u64,
+	pub out: u64,
+}
+
+/// Get a copy of the transaction with all input and output witnesses removed.
+///
+/// This includes the issuance rangeproofs, which are part of the input witness.
+pub fn strip_witness(tx: &Transaction) -> Transaction {
+	let mut stripped = tx.clone();
+	for input in stripped.input.iter_mut() {
+		input.witness = Default::default();
+	}
+	for output in stripped.output.iter_mut() {
+		output.witness = Default::default();
+	}
+	stripped
 }
 
 impl GetInfo<TransactionInfo> for Transaction {
 	fn get_info(&self, network: Network) -> TransactionInfo {
+		let non_witness_size = serialize(&strip_witness(self)).len();
+
 		TransactionInfo {
 			txid: Some(self.txid()),
 			wtxid: Some(self.wtxid()),
 			hash: Some(self.wtxid()),
 			version: Some(self.version),
 			locktime: Some(self.lock_time),
+			locktime_info: Some(self.lock_time.get_info(network)),
 			size: Some(serialize(self).len()),
 			weight: Some(self.weight() as usize),
-			vsize: Some((self.weight() / 4) as usize),
+			vsize: Some(self.weight() / 4),
+			witness_weight: Some(self.weight() - non_witness_size * 4),
+			non_witness_weight: Some(non_witness_size * 4),
 			inputs: Some(self.input.iter().map(|i| i.get_info(network)).collect()),
 			outputs: Some(self.output.iter().map(|o| o.get_info(network)).collect()),
+			fee: None,
+			balances: None,
+			balances_exclude_confidential: None,
+		}
+	}
+}
+
+/// Add the values and assets of the spent outputs to the inputs of the transaction info.
+///
+/// The outputs spent by the inputs are given in the same order as the inputs, or `None` if
+/// unknown. If all of them are known, the fee and the per-asset balances are computed as well.
+pub fn set_spent_outputs(
+	info: &mut TransactionInfo,
+	tx: &Transaction,
+	spent: &[Option<TxOut>],
+	network: Network,
+) {
+	if let Some(ref mut inputs) = info.inputs {
+		for (input, spent) in inputs.iter_mut().zip(spent.iter()) {
+			if let Some(ref txout) = *spent {
+				input.value = Some(txout.value.get_info(network));
+				input.asset = Some(txout.asset.get_info(network));
+			}
+		}
+	}
+
+	if spent.len() == tx.input.len() && spent.iter().all(|s| s.is_some()) {
+		let spent = spent.iter().map(|s| s.clone().unwrap()).collect::<Vec<_>>();
+		info.fee = fee_from_spent_outputs(tx, &spent, network.policy_asset());
+
+		let (balances, exclude_confidential) = asset_balances(tx, &spent);
+		info.balances = Some(balances);
+		info.balances_exclude_confidential = Some(exclude_confidential);
+	}
+}
+
+/// Sum the explicit input and output amounts of the transaction per asset.
+///
+/// Inputs and outputs with a confidential asset or value are excluded from the totals, in
+/// which case the second return value is `true`.
+pub fn asset_balances(
+	tx: &Transaction,
+	spent: &[TxOut],
+) -> (BTreeMap<AssetId, AssetBalanceInfo>, bool) {
+	let mut balances = BTreeMap::<AssetId, AssetBalanceInfo>::new();
+	let mut exclude_confidential = false;
+
+	for txout in spent {
+		match (txout.asset, txout.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				let balance = balances.entry(asset).or_default();
+				balance.in_ = balance.in_.saturating_add(value);
+			}
+			_ => exclude_confidential = true,
+		}
+	}
+	for txout in tx.output.iter() {
+		match (txout.asset, txout.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				let balance = balances.entry(asset).or_default();
+				balance.out = balance.out.saturating_add(value);
+			}
+			(_, confidential::Value::Null) => {}
+			_ => exclude_confidential = true,
+		}
+	}
+	(balances, exclude_confidential)
+}
+
+/// Compute the fee of the transaction from the outputs spent by its inputs.
+///
+/// This is only possible when there is a fee output and all inputs and outputs of the fee asset
+/// are explicit.
+pub fn fee_from_spent_outputs(
+	tx: &Transaction,
+	spent: &[TxOut],
+	policy_asset: Option<AssetId>,
+) -> Option<u64> {
+	let fee_output = tx.output.iter().find(|o| is_fee_output(o, policy_asset))?;
+	let fee_asset = fee_output.asset.explicit()?;
+
+	let mut total_in = 0u64;
+	for txout in spent {
+		match (txout.asset, txout.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				if asset == fee_asset {
+					total_in = total_in.checked_add(value)?;
+				}
+			}
+			_ => return None,
+		}
+	}
+
+	let mut total_out = 0u64;
+	for txout in tx.output.iter().filter(|o| !is_fee_output(o, Some(fee_asset))) {
+		match (txout.asset, txout.value) {
+			(confidential::Asset::Explicit(asset), confidential::Value::Explicit(value)) => {
+				if asset == fee_asset {
+					total_out = total_out.checked_add(value)?;
+				}
+			}
+			_ => return None,
+		}
+	}
+	total_in.checked_sub(total_out)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct TxidInfo {
+	pub txid: elements::Txid,
+	pub wtxid: elements::Wtxid,
+}
+
+impl TxidInfo {
+	pub fn from_tx(tx: &Transaction) -> TxidInfo {
+		TxidInfo {
+			txid: tx.txid(),
+			wtxid: tx.wtxid(),
+		}
+	}
+}
+
+/// The virtual size for the given weight, rounded up.
+pub fn vsize(weight: usize) -> usize {
+	(weight + 3) / 4
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize, Serialize)]
+pub struct FeeInfo {
+	pub fee: Option<u64>,
+	pub weight: usize,
+	pub vsize: usize,
+	pub fee_rate_sat_vb: Option<f64>,
+}
+
+/// Get the fee info of the transaction, summing all fee outputs of the policy asset.
+pub fn fee_info(tx: &Transaction, policy_asset: Option<AssetId>) -> FeeInfo {
+	let fees = tx.output.iter()
+		.filter(|o| is_fee_output(o, policy_asset))
+		.filter_map(|o| o.value.explicit())
+		.collect::<Vec<_>>();
+	let fee = if fees.is_empty() {
+		None
+	} else {
+		Some(fees.iter().sum())
+	};
+
+	let weight = tx.weight();
+	let vsize = vsize(weight);
+	FeeInfo {
+		fee: fee,
+		weight: weight,
+		vsize: vsize,
+		fee_rate_sat_vb: fee.map(|f| f as f64 / vsize as f64),
+	}
+}
+
+/// The ids of the asset and the reissuance token created by an issuance.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct IssuanceIdsInfo {
+	pub entropy: sha256::Midstate,
+	pub asset_id: AssetId,
+	pub token_id: AssetId,
+}
+
+impl IssuanceIdsInfo {
+	/// Compute the ids of a new issuance spending the given outpoint.
+	pub fn new_issuance(
+		prevout: OutPoint,
+		contract_hash: ContractHash,
+		confidential: bool,
+	) -> IssuanceIdsInfo {
+		IssuanceIdsInfo::from_entropy(
+			AssetId::generate_asset_entropy(prevout, contract_hash),
+			confidential,
+		)
+	}
+
+	/// Compute the ids from the issuance entropy.
+	pub fn from_entropy(entropy: sha256::Midstate, confidential: bool) -> IssuanceIdsInfo {
+		IssuanceIdsInfo {
+			entropy: entropy,
+			asset_id: AssetId::from_entropy(entropy),
+			token_id: AssetId::reissuance_token_from_entropy(entropy, confidential),
+		}
+	}
+}
+
+/// Check both ways to specify the outpoint and error if conflicting.
+pub fn outpoint_from_input_info(input: &InputInfo) -> Result<OutPoint, Error> {
+	let op1 = match input.prevout {
+		Some(ref op) => Some(
+			op.parse::<OutPoint>().map_err(|e| Error::InvalidField("prevout", e.to_string()))?,
+		),
+		None => None,
+	};
+	let op2 = match input.txid {
+		Some(txid) => match input.vout {
+			Some(vout) => Some(OutPoint {
+				txid: txid,
+				vout: vout,
+			}),
+			None => return Err(Error::MissingField("vout")),
+		},
+		None => None,
+	};
+
+	match (op1, op2) {
+		(Some(op1), Some(op2)) => {
+			if op1 != op2 {
+				return Err(Error::Conflict("Conflicting prevout information in input.".into()));
+			}
+			Ok(op1)
+		}
+		(Some(op), None) => Ok(op),
+		(None, Some(op)) => Ok(op),
+		(None, None) => Err(Error::MissingField("prevout")),
+	}
+}
+
+/// Parse a fixed-size byte array for the given field, reporting the length on mismatch.
+pub fn fixed_bytes<const N: usize>(field: &'static str, bytes: &[u8]) -> Result<[u8; N], Error> {
+	if bytes.len() != N {
+		return Err(Error::InvalidField(
+			field,
+			format!("expected {} bytes, got {}", N, bytes.len()),
+		));
+	}
+	let mut array = [0; N];
+	array.copy_from_slice(bytes);
+	Ok(array)
+}
+
+pub fn create_confidential_value(info: ConfidentialValueInfo) -> Result<confidential::Value, Error> {
+	match info.type_ {
+		ConfidentialType::Null => Ok(confidential::Value::Null),
+		ConfidentialType::Explicit => {
+			Ok(confidential::Value::Explicit(info.value.ok_or(Error::MissingField("value"))?))
+		}
+		ConfidentialType::Confidential => {
+			let comm = secp256k1_zkp::PedersenCommitment::from_slice(
+				info.commitment.ok_or(Error::MissingField("commitment"))?.bytes(),
+			)
+			.map_err(|e| Error::InvalidField("commitment", e.to_string()))?;
+			Ok(confidential::Value::Confidential(comm))
+		}
+	}
+}
+
+pub fn create_confidential_asset(info: ConfidentialAssetInfo) -> Result<confidential::Asset, Error> {
+	match info.type_ {
+		ConfidentialType::Null => Ok(confidential::Asset::Null),
+		ConfidentialType::Explicit => {
+			Ok(confidential::Asset::Explicit(info.asset.ok_or(Error::MissingField("asset"))?))
+		}
+		ConfidentialType::Confidential => {
+			let gen = secp256k1_zkp::Generator::from_slice(
+				info.commitment.ok_or(Error::MissingField("commitment"))?.bytes(),
+			)
+			.map_err(|e| Error::InvalidField("commitment", e.to_string()))?;
+			Ok(confidential::Asset::Confidential(gen))
 		}
 	}
 }
+
+pub fn create_confidential_nonce(info: ConfidentialNonceInfo) -> Result<confidential::Nonce, Error> {
+	match info.type_ {
+		ConfidentialType::Null => Ok(confidential::Nonce::Null),
+		ConfidentialType::Explicit => Ok(confidential::Nonce::Explicit(
+			info.nonce.ok_or(Error::MissingField("nonce"))?.to_byte_array(),
+		)),
+		ConfidentialType::Confidential => {
+			let comm = secp256k1_zkp::PublicKey::from_slice(
+				info.commitment.ok_or(Error::MissingField("commitment"))?.bytes(),
+			)
+			.map_err(|e| Error::InvalidField("commitment", e.to_string()))?;
+			Ok(confidential::Nonce::Confidential(comm))
+		}
+	}
+}
+
+pub fn create_asset_issuance(info: AssetIssuanceInfo) -> Result<AssetIssuance, Error> {
+	if info.is_reissuance.is_some() {
+		warn!("Field \"is_reissuance\" of asset issuance is ignored.");
+	}
+	if info.asset_id.is_some() {
+		warn!("Field \"asset_id\" of asset issuance is ignored.");
+	}
+	if info.token_id.is_some() {
+		warn!("Field \"token_id\" of asset issuance is ignored.");
+	}
+
+	Ok(AssetIssuance {
+		asset_blinding_nonce: secp256k1_zkp::Tweak::from_inner(fixed_bytes::<32>(
+			"asset_blinding_nonce",
+			&info.asset_blinding_nonce.ok_or(Error::MissingField("asset_blinding_nonce"))?.0[..],
+		)?)
+		.map_err(|e| Error::InvalidField("asset_blinding_nonce", e.to_string()))?,
+		asset_entropy: fixed_bytes::<32>(
+			"asset_entropy",
+			&info.asset_entropy.ok_or(Error::MissingField("asset_entropy"))?.0[..],
+		)?,
+		amount: create_confidential_value(info.amount.ok_or(Error::MissingField("amount"))?)?,
+		inflation_keys: create_confidential_value(
+			info.inflation_keys.ok_or(Error::MissingField("inflation_keys"))?,
+		)?,
+	})
+}
+
+/// Find the opcode with the given name, like `OP_CHECKSIG`.
+fn opcode_from_name(name: &str) -> Option<elements::opcodes::All> {
+	match name {
+		"OP_0" | "OP_FALSE" => return Some(elements::opcodes::all::OP_PUSHBYTES_0),
+		"OP_TRUE" => return Some(elements::opcodes::all::OP_PUSHNUM_1),
+		"OP_1NEGATE" => return Some(elements::opcodes::all::OP_PUSHNUM_NEG1),
+		_ => {}
+	}
+	(0..=255u8).map(elements::opcodes::All::from).find(|op| format!("{:?}", op) == name)
+}
+
+/// Assemble a script from its assembly representation.
+///
+/// Supported tokens are opcode names like `OP_CHECKSIG`, decimal numbers and hex data pushes
+/// enclosed in angle brackets like `<deadbeef>`. As in the output of [Script::asm], explicit
+/// push opcodes like `OP_PUSHBYTES_4` are followed by the pushed data in hex.
+pub fn asm_to_script(asm: &str) -> Result<Script, Error> {
+	let invalid = |msg: String| Error::InvalidField("asm", msg);
+	let mut builder = elements::script::Builder::new();
+	let mut tokens = asm.split_whitespace();
+	while let Some(token) = tokens.next() {
+		builder = if token.starts_with("<") && token.ends_with(">") {
+			let data = hex::decode(&token[1..token.len() - 1])
+				.map_err(|_| invalid(format!("invalid hex push: {}", token)))?;
+			builder.push_slice(&data)
+		} else if token.starts_with("OP_PUSHBYTES_") || token.starts_with("OP_PUSHDATA") {
+			let data = match tokens.next() {
+				Some(hex) => hex::decode(hex.trim_start_matches('<').trim_end_matches('>'))
+					.map_err(|_| invalid(format!("invalid hex push: {}", hex)))?,
+				None if token == "OP_PUSHBYTES_0" => Vec::new(),
+				None => return Err(invalid(format!("missing data after {}", token))),
+			};
+			builder.push_slice(&data)
+		} else if token.starts_with("OP_") {
+			match token.trim_start_matches("OP_").parse::<i64>() {
+				Ok(n) if n >= 1 && n <= 16 => builder.push_int(n),
+				_ => builder.push_opcode(
+					opcode_from_name(token)
+						.ok_or_else(|| invalid(format!("unknown opcode: {}", token)))?,
+				),
+			}
+		} else if let Ok(n) = token.parse::<i64>() {
+			builder.push_int(n)
+		} else {
+			return Err(invalid(format!("invalid token: {}", token)));
+		}
+	}
+	Ok(builder.into_script())
+}
+
+pub fn create_script_sig(ss: InputScriptInfo) -> Result<Script, Error> {
+	if let Some(hex) = ss.hex {
+		if ss.asm.is_some() {
+			warn!("Field \"asm\" of input is ignored.");
+		}
+
+		Ok(hex.0.into())
+	} else if let Some(asm) = ss.asm {
+		asm_to_script(&asm)
+	} else {
+		Err(Error::MissingField("script_sig"))
+	}
+}
+
+/// Create the pegin witness from the mainchain transaction and its merkle proof.
+///
+/// The mainchain transaction must be the one spent by the input and must be included in the
+/// merkle proof. The value is taken from the spent mainchain output if omitted.
+pub fn create_pegin_witness(
+	pd: PeginDataInfo,
+	prevout: bitcoin::OutPoint,
+) -> Result<Vec<Vec<u8>>, Error> {
+	if let Some(ref outpoint) = pd.outpoint {
+		let outpoint = outpoint
+			.parse::<bitcoin::OutPoint>()
+			.map_err(|e| Error::InvalidField("pegin_data.outpoint", e.to_string()))?;
+		if prevout != outpoint {
+			return Err(Error::Conflict(
+				"Outpoint in \"pegin_data\" does not correspond to input value.".into(),
+			));
+		}
+	}
+
+	let mainchain_tx: bitcoin::Transaction =
+		bitcoin::consensus::encode::deserialize(&pd.mainchain_tx_hex.0)
+			.map_err(|e| Error::InvalidField("pegin_data.mainchain_tx_hex", e.to_string()))?;
+	if mainchain_tx.txid() != prevout.txid {
+		return Err(Error::Conflict(
+			"Mainchain transaction in \"pegin_data\" is not the one spent by the input.".into(),
+		));
+	}
+	let mainchain_value = mainchain_tx
+		.output
+		.get(prevout.vout as usize)
+		.ok_or_else(|| {
+			Error::InvalidField("pegin_data.mainchain_tx_hex", "spent output does not exist".into())
+		})?
+		.value;
+	let value = pd.value.unwrap_or(mainchain_value);
+	if value != mainchain_value {
+		return Err(Error::Conflict(
+			"Value in \"pegin_data\" does not correspond to the mainchain output.".into(),
+		));
+	}
+
+	let merkle_block: bitcoin::MerkleBlock =
+		bitcoin::consensus::encode::deserialize(&pd.merkle_proof.0)
+			.map_err(|e| Error::InvalidField("pegin_data.merkle_proof", e.to_string()))?;
+	let mut matches = Vec::new();
+	let mut indexes = Vec::new();
+	merkle_block
+		.extract_matches(&mut matches, &mut indexes)
+		.map_err(|e| Error::InvalidField("pegin_data.merkle_proof", format!("{:?}", e)))?;
+	if !matches.contains(&prevout.txid) {
+		return Err(Error::Conflict(
+			"Merkle proof in \"pegin_data\" does not include the mainchain transaction.".into(),
+		));
+	}
+	if let Some(block) = pd.referenced_block {
+		if merkle_block.header.block_hash() != block {
+			return Err(Error::Conflict(
+				"Merkle proof in \"pegin_data\" is not for the referenced block.".into(),
+			));
+		}
+	}
+
+	if pd.claim_script.0.is_empty() {
+		return Err(Error::InvalidField("pegin_data.claim_script", "claim script is empty".into()));
+	}
+
+	let asset = match create_confidential_asset(pd.asset)? {
+		confidential::Asset::Explicit(asset) => asset,
+		_ => {
+			return Err(Error::InvalidField("pegin_data.asset", "asset should be explicit".into()))
+		}
+	};
+	// The last three elements are pushed as they are.
+	Ok(vec![
+		serialize(&value),
+		serialize(&asset),
+		serialize(&pd.genesis_hash),
+		pd.claim_script.0,
+		pd.mainchain_tx_hex.0,
+		pd.merkle_proof.0,
+	])
+}
+
+pub fn create_input_witness(
+	info: Option<InputWitnessInfo>,
+	pd: Option<PeginDataInfo>,
+	prevout: bitcoin::OutPoint,
+) -> Result<TxInWitness, Error> {
+	let pegin_witness = if info.is_some() && info.as_ref().unwrap().pegin_witness.is_some() {
+		if pd.is_some() {
+			warn!("Field \"pegin_data\" of input is ignored.");
+		}
+		info.as_ref().unwrap().pegin_witness.clone().unwrap().iter().map(|h| h.clone().0).collect()
+	} else if let Some(pd) = pd {
+		create_pegin_witness(pd, prevout)?
+	} else {
+		Default::default()
+	};
+
+	if info.as_ref().map(|i| i.pegin_witness_labeled.is_some()).unwrap_or(false) {
+		warn!("Field \"pegin_witness_labeled\" of input witness is ignored.");
+	}
+
+	let rangeproof = |field: &'static str, bytes: &HexBytes| {
+		RangeProof::from_slice(bytes.bytes())
+			.map(Box::new)
+			.map_err(|e| Error::InvalidField(field, e.to_string()))
+	};
+
+	if let Some(wi) = info {
+		Ok(TxInWitness {
+			amount_rangeproof: match wi.amount_rangeproof {
+				Some(ref x) => Some(rangeproof("amount_rangeproof", x)?),
+				None => None,
+			},
+			inflation_keys_rangeproof: match wi.inflation_keys_rangeproof {
+				Some(ref x) => Some(rangeproof("inflation_keys_rangeproof", x)?),
+				None => None,
+			},
+			script_witness: match wi.script_witness {
+				Some(ref w) => w.iter().map(|h| h.clone().0).collect(),
+				None => Vec::new(),
+			},
+			pegin_witness: pegin_witness,
+		})
+	} else {
+		Ok(TxInWitness {
+			pegin_witness: pegin_witness,
+			..Default::default()
+		})
+	}
+}
+
+pub fn create_input(input: InputInfo) -> Result<TxIn, Error> {
+	let has_issuance = input.has_issuance.unwrap_or(input.asset_issuance.is_some());
+	let is_pegin = input.is_pegin.unwrap_or(input.pegin_data.is_some());
+	let prevout = outpoint_from_input_info(&input)?;
+	if input.value.is_some() {
+		warn!("Field \"value\" of input is ignored.");
+	}
+	if input.asset.is_some() {
+		warn!("Field \"asset\" of input is ignored.");
+	}
+
+	Ok(TxIn {
+		previous_output: prevout,
+		script_sig: match input.script_sig {
+			Some(ss) => create_script_sig(ss)?,
+			None => Default::default(),
+		},
+		sequence: input.sequence.unwrap_or_default(),
+		is_pegin: is_pegin,
+		asset_issuance: if has_issuance {
+			match input.asset_issuance {
+				Some(ai) => create_asset_issuance(ai)?,
+				None => Default::default(),
+			}
+		} else {
+			if input.asset_issuance.is_some() {
+				warn!("Field \"asset_issuance\" of input is ignored.");
+			}
+			Default::default()
+		},
+		witness: {
+			let btc_prevout = bitcoin::OutPoint::new(
+				bitcoin::Txid::from_raw_hash(prevout.txid.into()),
+				prevout.vout,
+			);
+			create_input_witness(input.witness, input.pegin_data, btc_prevout)?
+		},
+	})
+}
+
+pub fn create_pegout_script_pubkey(
+	spk: hal::tx::OutputScriptInfo,
+) -> Result<bitcoin::ScriptBuf, Error> {
+	if spk.type_.is_some() {
+		warn!("Field \"type\" of output is ignored.");
+	}
+
+	if let Some(hex) = spk.hex {
+		if spk.asm.is_some() {
+			warn!("Field \"asm\" of output is ignored.");
+		}
+		if spk.address.is_some() {
+			warn!("Field \"address\" of output is ignored.");
+		}
+
+		//TODO(stevenroose) do script sanity check to avoid blackhole?
+		Ok(hex.0.into())
+	} else if let Some(asm) = spk.asm {
+		if spk.address.is_some() {
+			warn!("Field \"address\" of output is ignored.");
+		}
+
+		Ok(asm_to_script(&asm)?.into_bytes().into())
+	} else if let Some(address) = spk.address {
+		Ok(address.assume_checked().script_pubkey())
+	} else {
+		Err(Error::MissingField("script_pub_key"))
+	}
+}
+
+/// Create a scriptPubKey from its JSON info.
+///
+/// The network of the address used, if any, is stored in `used_network`, erroring if it differs
+/// from the network already there. Addresses with the params of the `custom` network, if given,
+/// are detected as that network.
+pub fn create_script_pubkey(
+	spk: OutputScriptInfo,
+	custom: Option<&'static CustomNetwork>,
+	used_network: &mut Option<Network>,
+) -> Result<Script, Error> {
+	if spk.type_.is_some() {
+		warn!("Field \"type\" of output is ignored.");
+	}
+
+	if let Some(hex) = spk.hex {
+		if spk.asm.is_some() {
+			warn!("Field \"asm\" of output is ignored.");
+		}
+		if spk.unblinded_address.is_some() {
+			warn!("Field \"address\" of output is ignored.");
+		}
+
+		//TODO(stevenroose) do script sanity check to avoid blackhole?
+		Ok(hex.0.into())
+	} else if let Some(asm) = spk.asm {
+		if spk.unblinded_address.is_some() {
+			warn!("Field \"address\" of output is ignored.");
+		}
+
+		asm_to_script(&asm)
+	} else if let Some(address) = spk.unblinded_address {
+		// Error if another network had already been used.
+		let net = match custom {
+			Some(custom) => Network::from_params_with_custom(address.params, custom),
+			None => Network::from_params(address.params),
+		};
+		let net = net.ok_or_else(|| {
+			Error::InvalidField("unblinded_address", "unknown address network".into())
+		})?;
+		if used_network.replace(net).unwrap_or(net) != net {
+			return Err(Error::Conflict(
+				"Addresses for different networks are used in the output scripts.".into(),
+			));
+		}
+
+		Ok(address.script_pubkey())
+	} else {
+		Err(Error::MissingField("script_pub_key"))
+	}
+}
+
+pub fn create_output_witness(w: OutputWitnessInfo) -> Result<TxOutWitness, Error> {
+	Ok(TxOutWitness {
+		surjection_proof: match w.surjection_proof {
+			Some(ref x) => Some(Box::new(
+				SurjectionProof::from_slice(x.bytes())
+					.map_err(|e| Error::InvalidField("surjection_proof", e.to_string()))?,
+			)),
+			None => None,
+		},
+		rangeproof: match w.rangeproof {
+			Some(ref x) => Some(Box::new(
+				RangeProof::from_slice(x.bytes())
+					.map_err(|e| Error::InvalidField("rangeproof", e.to_string()))?,
+			)),
+			None => None,
+		},
+	})
+}
+
+// The network used is the bitcoin Network
+// so we do not maintain the network on the &mut used_network
+pub fn create_script_pubkey_from_pegout_data(pd: PegoutDataInfo) -> Result<Script, Error> {
+	let mut builder = elements::script::Builder::new()
+		.push_opcode(elements::opcodes::all::OP_RETURN)
+		.push_slice(&pd.genesis_hash.to_byte_array())
+		.push_slice(&create_pegout_script_pubkey(pd.script_pub_key)?.as_bytes());
+	for d in pd.extra_data {
+		builder = builder.push_slice(&d.0);
+	}
+	Ok(builder.into_script())
+}
+
+/// Create an output from its JSON info.
+///
+/// The network of the address used in the output, if any, is stored in `used_network`,
+/// erroring if it differs from the network already there, see [create_script_pubkey].
+pub fn create_output(
+	output: OutputInfo,
+	custom: Option<&'static CustomNetwork>,
+	used_network: &mut Option<Network>,
+) -> Result<TxOut, Error> {
+	let value = create_confidential_value(output.value.ok_or(Error::MissingField("value"))?)?;
+	let asset = create_confidential_asset(output.asset.ok_or(Error::MissingField("asset"))?)?;
+	let witness = match output.witness {
+		Some(w) => create_output_witness(w)?,
+		None => Default::default(),
+	};
+	if output.op_return_data.is_some() {
+		warn!("Field \"op_return_data\" of output is ignored.");
+	}
+
+	// Mixing explicit and confidential value and asset requires the proof of the
+	// confidential one.
+	if value.is_explicit() && asset.is_confidential() && witness.surjection_proof.is_none() {
+		return Err(Error::Conflict(
+			"Confidential asset with explicit value requires a surjection proof.".into(),
+		));
+	}
+	if value.is_confidential() && asset.is_explicit() && witness.rangeproof.is_none() {
+		return Err(Error::Conflict(
+			"Confidential value with explicit asset requires a rangeproof.".into(),
+		));
+	}
+
+	Ok(TxOut {
+		asset: asset,
+		value: value,
+		nonce: match output.nonce {
+			Some(nonce) => create_confidential_nonce(nonce)?,
+			None => confidential::Nonce::Null,
+		},
+		script_pubkey: if let Some(spk) = output.script_pub_key {
+			if output.pegout_data.is_some() {
+				warn!("Field \"pegout_data\" of output is ignored.");
+			}
+			create_script_pubkey(spk, custom, used_network)?
+		} else if let Some(pd) = output.pegout_data {
+			match value {
+				confidential::Value::Explicit(v) => {
+					if v != pd.value {
+						return Err(Error::Conflict(
+							"Value in \"pegout_data\" does not correspond to output value.".into(),
+						));
+					}
+				}
+				_ => {
+					return Err(Error::InvalidField(
+						"value",
+						"explicit value is required for pegout data".into(),
+					))
+				}
+			}
+			if asset != create_confidential_asset(pd.asset.clone())? {
+				return Err(Error::Conflict(
+					"Asset in \"pegout_data\" does not correspond to output value.".into(),
+				));
+			}
+			create_script_pubkey_from_pegout_data(pd)?
+		} else {
+			Default::default()
+		},
+		witness: witness,
+	})
+}
+
+/// Create a transaction from its JSON info.
+///
+/// Keeps track of which network has been used in the output addresses in `used_network` and
+/// errors if two different networks are used, see [create_script_pubkey].
+pub fn create_transaction(
+	info: TransactionInfo,
+	custom: Option<&'static CustomNetwork>,
+	used_network: &mut Option<Network>,
+) -> Result<Transaction, Error> {
+	// Fields that are ignored.
+	if info.txid.is_some() {
+		warn!("Field \"txid\" is ignored.");
+	}
+	if info.hash.is_some() {
+		warn!("Field \"hash\" is ignored.");
+	}
+	if info.size.is_some() {
+		warn!("Field \"size\" is ignored.");
+	}
+	if info.weight.is_some() {
+		warn!("Field \"weight\" is ignored.");
+	}
+	if info.vsize.is_some() {
+		warn!("Field \"vsize\" is ignored.");
+	}
+	if info.witness_weight.is_some() {
+		warn!("Field \"witness_weight\" is ignored.");
+	}
+	if info.non_witness_weight.is_some() {
+		warn!("Field \"non_witness_weight\" is ignored.");
+	}
+	if info.locktime_info.is_some() {
+		warn!("Field \"locktime_info\" is ignored.");
+	}
+	if info.fee.is_some() {
+		warn!("Field \"fee\" is ignored.");
+	}
+	if info.balances.is_some() {
+		warn!("Field \"balances\" is ignored.");
+	}
+	if info.balances_exclude_confidential.is_some() {
+		warn!("Field \"balances_exclude_confidential\" is ignored.");
+	}
+
+	Ok(Transaction {
+		version: info.version.ok_or(Error::MissingField("version"))?,
+		lock_time: info.locktime.ok_or(Error::MissingField("locktime"))?,
+		input: info
+			.inputs
+			.ok_or(Error::MissingField("inputs"))?
+			.into_iter()
+			.enumerate()
+			.map(|(i, input)| create_input(input).map_err(|e| Error::Input(i, Box::new(e))))
+			.collect::<Result<_, _>>()?,
+		output: info
+			.outputs
+			.ok_or(Error::MissingField("outputs"))?
+			.into_iter()
+			.enumerate()
+			.map(|(i, output)| {
+				create_output(output, custom, used_network)
+					.map_err(|e| Error::Output(i, Box::new(e)))
+			})
+			.collect::<Result<_, _>>()?,
+	})
+}